@@ -1,3 +1,6 @@
+// `#[derive(Fail)]` expands to impls inside a const item.
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate failure;
 #[macro_use]
@@ -7,8 +10,11 @@ extern crate log;
 #[macro_use]
 extern crate serde_derive;
 extern crate chrono;
+extern crate flate2;
 extern crate rayon;
 extern crate serde;
 extern crate serde_json;
+extern crate sha2;
+extern crate zip;
 
 pub mod nanobot;
@@ -1,8 +1,9 @@
+extern crate icfp2018;
 extern crate loggerv;
-#[macro_use]
 extern crate structopt;
-extern crate icfp2018;
 
+use std::path::PathBuf;
+use std::time::Duration;
 use structopt::StructOpt;
 
 use icfp2018::nanobot;
@@ -12,6 +13,14 @@ use icfp2018::nanobot::Result;
 struct Opt {
     #[structopt(short = "v", parse(from_occurrences))]
     verbose: u64,
+    /// Directory which has trace/ and submit/. Defaults to $ICFP2018_CONTEST_DIR,
+    /// or contest/ in the repository.
+    #[structopt(long = "contest-dir", parse(from_os_str))]
+    contest_dir: Option<PathBuf>,
+    /// Directory of *_src.mdl and *_tgt.mdl. Defaults to $ICFP2018_PROBLEMS_DIR,
+    /// or model/ in the contest directory.
+    #[structopt(long = "problems-dir", parse(from_os_str))]
+    problems_dir: Option<PathBuf>,
     #[structopt(subcommand)]
     cmd: Command,
 }
@@ -20,29 +29,358 @@ struct Opt {
 enum Command {
     #[structopt(name = "run")]
     Run {
+        /// Runs every given number of bots and keeps the best trace.
         #[structopt(long = "bots")]
-        bots: Option<usize>,
+        bots: Vec<usize>,
         #[structopt(long = "src")]
         src: Option<String>,
         #[structopt(long = "tgt")]
         target: Option<String>,
         #[structopt(long = "output")]
         output: Option<String>,
+        /// Time limit in seconds.
+        #[structopt(long = "timeout")]
+        timeout: Option<u64>,
+        /// Seed for randomized tie-breaking. The first seed with --seeds.
+        #[structopt(long = "seed")]
+        seed: Option<u64>,
+        /// Runs every AI with this many seeds and keeps the best trace.
+        #[structopt(long = "seeds")]
+        seeds: Option<usize>,
     },
     #[structopt(name = "ci")]
-    Ci {},
+    Ci {
+        /// Time limit in seconds for each model.
+        #[structopt(long = "model-timeout")]
+        model_timeout: Option<u64>,
+        /// Time limit in seconds for the whole run.
+        #[structopt(long = "timeout")]
+        timeout: Option<u64>,
+        /// Seed for randomized tie-breaking. The first seed with --seeds.
+        #[structopt(long = "seed")]
+        seed: Option<u64>,
+        /// Runs every AI with this many seeds and keeps the best trace.
+        #[structopt(long = "seeds")]
+        seeds: Option<usize>,
+        /// Models which fail the run if they regress or fail. Every model by
+        /// default.
+        #[structopt(long = "fail-on")]
+        fail_on: Vec<String>,
+        /// Solves only models which match any of these globs, e.g. 'FA0*'.
+        #[structopt(long = "only")]
+        only: Vec<String>,
+        /// Runs only strategies which match any of these globs, e.g. 'Many(2)'.
+        #[structopt(long = "strategy")]
+        strategies: Vec<String>,
+        /// Solves again even if the result is cached.
+        #[structopt(long = "no-cache")]
+        no_cache: bool,
+        /// Appends a JSON line for every task to this file. Defaults to
+        /// telemetry.jsonl in the report directory.
+        #[structopt(long = "telemetry", parse(from_os_str))]
+        telemetry: Option<PathBuf>,
+        /// Listens on this address, e.g. '0.0.0.0:7878', and hands out jobs
+        /// to workers instead of solving locally.
+        #[structopt(long = "listen")]
+        listen: Option<String>,
+    },
+    /// Solves jobs of a ci run started with --listen.
+    #[structopt(name = "worker")]
+    Worker {
+        /// The address of the coordinator, e.g. 'ci-host:7878'.
+        #[structopt(long = "connect")]
+        connect: String,
+        /// Number of jobs to solve in parallel. Defaults to the number of
+        /// CPUs.
+        #[structopt(long = "jobs")]
+        jobs: Option<usize>,
+    },
+    /// Packages the best traces into a zip archive for submission.
+    #[structopt(name = "package")]
+    Package {
+        /// Defaults to trace.zip in the submit directory. The SHA-256 is
+        /// written to <output>.sha256.
+        #[structopt(long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+        /// Writes the archive even if some problems have no trace.
+        #[structopt(long = "allow-missing")]
+        allow_missing: bool,
+    },
+    /// Simulates a trace and prints its energy and whether it is valid.
+    #[structopt(name = "simulate")]
+    Simulate {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        #[structopt(long = "trace")]
+        trace: String,
+        /// Prints JSON instead of text.
+        #[structopt(long = "json")]
+        json: bool,
+    },
+    /// Prints structural statistics of a model.
+    #[structopt(name = "info")]
+    Info {
+        #[structopt(parse(from_os_str))]
+        model: PathBuf,
+    },
+    /// Prints slices of a model, or of the state in the middle of a trace.
+    #[structopt(name = "render")]
+    Render {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        /// Renders the simulator state with bots and volatile cells.
+        #[structopt(long = "trace")]
+        trace: Option<String>,
+        /// Stops the trace after this many time steps. Defaults to the end.
+        #[structopt(long = "time-step")]
+        time_step: Option<usize>,
+        /// x, y or z.
+        #[structopt(long = "axis", default_value = "y")]
+        axis: nanobot::Axis,
+        /// Renders only this slice. Every slice with a voxel or a bot by
+        /// default.
+        #[structopt(long = "index")]
+        index: Option<i32>,
+    },
+    /// Writes an isometric image of a model, or frames of a trace.
+    #[structopt(name = "image")]
+    Image {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        /// Renders the state at the end of the trace, with bots.
+        #[structopt(long = "trace")]
+        trace: Option<String>,
+        /// Writes a frame every this many time steps of the trace, named
+        /// like <output>-000012.png.
+        #[structopt(long = "every")]
+        every: Option<usize>,
+        /// Pixels per half the width of a voxel.
+        #[structopt(long = "scale")]
+        scale: Option<usize>,
+        /// .ppm for PPM, and PNG otherwise.
+        #[structopt(long = "output", parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Writes the exposed surface of a model as an OBJ or STL mesh.
+    #[structopt(name = "mesh")]
+    Mesh {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        /// Writes the matrix at the end of the trace instead.
+        #[structopt(long = "trace")]
+        trace: Option<String>,
+        /// .obj or .stl.
+        #[structopt(long = "output", parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Steps through a trace interactively, forward and back.
+    #[structopt(name = "debug")]
+    Debug {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        #[structopt(long = "trace")]
+        trace: String,
+    },
+    /// Prints what bots spend time steps on, and where they go, in a trace.
+    #[structopt(name = "traffic")]
+    Traffic {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        #[structopt(long = "trace")]
+        trace: String,
+        /// Writes a heatmap image of visits, .ppm for PPM and PNG otherwise.
+        #[structopt(long = "heatmap", parse(from_os_str))]
+        heatmap: Option<PathBuf>,
+        /// x, y or z.
+        #[structopt(long = "axis", default_value = "y")]
+        axis: nanobot::Axis,
+        /// The slice of the heatmap. The sum of every slice by default.
+        #[structopt(long = "index")]
+        index: Option<i32>,
+    },
+    /// Writes the playback of a trace as JSON lines, a line per time step.
+    #[structopt(name = "playback")]
+    Playback {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        #[structopt(long = "trace")]
+        trace: String,
+        /// Defaults to stdout.
+        #[structopt(long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Rewrites a valid trace to use less energy.
+    #[structopt(name = "optimize")]
+    Optimize {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        trace: String,
+        #[structopt(long = "output", parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Compares two traces of the same problem.
+    #[structopt(name = "diff")]
+    Diff {
+        #[structopt(long = "src")]
+        src: Option<String>,
+        #[structopt(long = "tgt")]
+        target: Option<String>,
+        a: String,
+        b: String,
+    },
+    /// Prints statistics of a .nbt trace.
+    #[structopt(name = "stats")]
+    Stats {
+        #[structopt(parse(from_os_str))]
+        trace: PathBuf,
+    },
+    /// Prints a .nbt trace in the text format.
+    #[structopt(name = "disasm")]
+    Disasm {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        /// Defaults to stdout.
+        #[structopt(long = "output", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Writes a .nbt trace from the text format.
+    #[structopt(name = "asm")]
+    Asm {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(long = "output", parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Prints the expected contest score of the best traces.
+    #[structopt(name = "score")]
+    Score,
 }
 
 fn main() -> Result<()> {
     let opt = Opt::from_args();
     loggerv::init_with_verbosity(opt.verbose).unwrap();
+    if let Some(dir) = opt.contest_dir {
+        nanobot::set_contest_dir(dir);
+    }
+    if let Some(dir) = opt.problems_dir {
+        nanobot::set_problems_dir(dir);
+    }
     match opt.cmd {
         Command::Run {
             bots,
             src,
             target,
             output,
-        } => nanobot::run(bots, src, target, output),
-        Command::Ci {} => nanobot::ci(),
+            timeout,
+            seed,
+            seeds,
+        } => nanobot::run(
+            bots,
+            src,
+            target,
+            output,
+            timeout.map(Duration::from_secs),
+            seed.unwrap_or(0),
+            seeds.unwrap_or(if seed.is_some() { 1 } else { 0 }),
+        ),
+        Command::Ci {
+            model_timeout,
+            timeout,
+            seed,
+            seeds,
+            fail_on,
+            only,
+            strategies,
+            no_cache,
+            telemetry,
+            listen,
+        } => nanobot::ci(&nanobot::CiConfig {
+            model_timeout: model_timeout.map(Duration::from_secs),
+            timeout: timeout.map(Duration::from_secs),
+            seeds: seeds.unwrap_or(if seed.is_some() { 1 } else { 0 }),
+            seed: seed.unwrap_or(0),
+            fail_on,
+            only,
+            strategies,
+            no_cache,
+            telemetry: Some(telemetry.unwrap_or_else(nanobot::default_telemetry_path)),
+            listen,
+        }),
+        Command::Worker { connect, jobs } => nanobot::work(&connect, jobs).map(|_| ()),
+        Command::Package {
+            output,
+            allow_missing,
+        } => nanobot::package(output, allow_missing),
+        Command::Score => nanobot::print_scores(),
+        Command::Info { model } => nanobot::print_info(&model),
+        Command::Render {
+            src,
+            target,
+            trace,
+            time_step,
+            axis,
+            index,
+        } => nanobot::print_render(src, target, trace, time_step, axis, index),
+        Command::Image {
+            src,
+            target,
+            trace,
+            every,
+            scale,
+            output,
+        } => nanobot::write_image(src, target, trace, every, scale, &output),
+        Command::Mesh {
+            src,
+            target,
+            trace,
+            output,
+        } => nanobot::write_mesh(src, target, trace, &output),
+        Command::Debug { src, target, trace } => nanobot::debug(src, target, trace),
+        Command::Traffic {
+            src,
+            target,
+            trace,
+            heatmap,
+            axis,
+            index,
+        } => nanobot::print_traffic(src, target, trace, axis, index, heatmap.as_deref()),
+        Command::Playback {
+            src,
+            target,
+            trace,
+            output,
+        } => nanobot::write_playback(src, target, trace, output.as_deref()),
+        Command::Optimize {
+            src,
+            target,
+            trace,
+            output,
+        } => nanobot::write_optimized(src, target, trace, &output),
+        Command::Diff { src, target, a, b } => nanobot::print_diff(src, target, a, b),
+        Command::Stats { trace } => nanobot::print_stats(&trace),
+        Command::Disasm { input, output } => nanobot::disasm(&input, output.as_deref()),
+        Command::Asm { input, output } => nanobot::asm(&input, &output),
+        Command::Simulate {
+            src,
+            target,
+            trace,
+            json,
+        } => nanobot::print_simulation(src, target, trace, json),
     }
 }
@@ -1,29 +1,93 @@
-use std::collections::HashSet;
+use std;
+use std::collections::{BTreeMap, HashSet};
 
 use super::bot::*;
+use super::deadline::*;
+use super::model::*;
 use super::prelude::*;
+use super::render::LEGEND;
 use super::system::*;
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub enum Ai {
     Many(usize),
+    // The reference solution of the contest. See `Sweep`.
+    Default,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Strategy {
+    pub ai: Ai,
+    // Seed for randomized tie-breaking. `None` keeps the fixed order.
+    pub seed: Option<u64>,
+}
+
+impl Ai {
+    /// Bump this when an AI produces different traces, so that cached ci
+    /// results of the AI are discarded.
+    pub fn version(&self) -> u32 {
+        match self {
+            Ai::Many(_) => 1,
+            Ai::Default => 1,
+        }
+    }
+}
+
+impl From<Ai> for Strategy {
+    fn from(ai: Ai) -> Strategy {
+        Strategy { ai, seed: None }
+    }
+}
+
+impl std::fmt::Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.seed {
+            Some(seed) => write!(f, "{:?}-seed{}", self.ai, seed),
+            None => write!(f, "{:?}", self.ai),
+        }
+    }
+}
+
+impl Strategy {
+    /// Each AI with `k` seeds, `seed`, `seed + 1`, ..., or without a seed if
+    /// `k` is zero.
+    pub fn with_seeds(ais: &[Ai], seed: u64, k: usize) -> Vec<Strategy> {
+        if k == 0 {
+            return ais.iter().map(|ai| Strategy::from(*ai)).collect();
+        }
+        ais.iter()
+            .flat_map(|ai| {
+                (seed..seed + k as u64).map(move |seed| Strategy {
+                    ai: *ai,
+                    seed: Some(seed),
+                })
+            })
+            .collect()
+    }
 }
 
 pub struct Many {
     bots: usize,
+    seed: Option<u64>,
 }
 
 impl Many {
-    pub fn new(bots: usize) -> Many {
-        Many { bots }
+    pub fn new(bots: usize, seed: Option<u64>) -> Many {
+        Many { bots, seed }
     }
 
-    pub fn solve(&mut self, sys: &mut System) -> Result<()> {
+    pub fn solve(&mut self, sys: &mut System, deadline: &Deadline) -> Result<()> {
+        if let Some(seed) = self.seed {
+            sys.randomize(seed);
+        }
         let origin = Cord::new(0, 0, 0);
         let origin_set = vec![origin].into_iter().collect::<HashSet<_>>();
 
         let mut wait_cont = 0;
         while !sys.priority_targets.priority_targets.is_empty() {
+            if deadline.is_expired() {
+                return Err(Timeout.into());
+            }
             let targets = sys.free_priority_targets();
             let cmd = {
                 if self.bots > 1 {
@@ -40,6 +104,7 @@ impl Many {
             if let Cmd::Wait = cmd {
                 wait_cont += 1;
                 if wait_cont == 10 {
+                    debug!("Deadlock:\n{}\n{}", LEGEND, sys.render_bot_layers());
                     return Err(NanoBotError.into());
                 }
             } else {
@@ -48,7 +113,8 @@ impl Many {
             sys.assert_execute_cmd(cmd);
         }
 
-        // Return to origin and fusion
+        // Return to origin and fusion. This is cheap, so the target which is
+        // built is not thrown away even if the deadline expires.
         loop {
             if sys.bots.len() == 1 {
                 if sys.current_bot().pos == origin {
@@ -87,6 +153,7 @@ impl Many {
                 if let Cmd::Wait = cmd {
                     wait_cont += 1;
                     if wait_cont == 10 {
+                        debug!("Deadlock:\n{}\n{}", LEGEND, sys.render_bot_layers());
                         return Err(NanoBotError.into());
                     }
                 } else {
@@ -97,3 +164,101 @@ impl Many {
         }
     }
 }
+
+// A single bot in High harmonics sweeps the model layer by layer, filling or
+// voiding the voxel below itself. Groundedness does not matter in High
+// harmonics, so it works for any model whose column at x = 0, z = 0 is empty,
+// which every contest model satisfies. It is expensive, but serves as a
+// fallback and as the baseline of scores.
+pub struct Sweep<'a> {
+    model: &'a Model,
+}
+
+impl<'a> Sweep<'a> {
+    pub fn new(model: &'a Model) -> Sweep<'a> {
+        Sweep { model }
+    }
+
+    fn execute(sys: &mut System, cmd: Cmd) -> Result<()> {
+        match sys.execute_cmd(cmd) {
+            CmdResult::Continue | CmdResult::Halt => Ok(()),
+            _ => Err(NanoBotError.into()),
+        }
+    }
+
+    // Moves along x, z and then y, at most 15 voxels per SMove.
+    fn move_to(sys: &mut System, to: Cord) -> Result<()> {
+        let from = sys.current_bot().pos;
+        let d = to - from;
+        for &(len, unit) in &[
+            (d.dx, CordDiff::new(1, 0, 0)),
+            (d.dz, CordDiff::new(0, 0, 1)),
+            (d.dy, CordDiff::new(0, 1, 0)),
+        ] {
+            let mut len = len;
+            while len != 0 {
+                let step = len.clamp(-15, 15);
+                let diff = CordDiff::new(unit.dx * step, unit.dy * step, unit.dz * step);
+                Sweep::execute(sys, Cmd::SMove(LongLinear(diff)))?;
+                len -= step;
+            }
+        }
+        Ok(())
+    }
+
+    // Targets by layer, in boustrophedon order in each layer.
+    fn layers(&self) -> BTreeMap<i32, Vec<Cord>> {
+        let mut layers = BTreeMap::new();
+        for c in &self.model.targets {
+            layers.entry(c.y).or_insert_with(Vec::new).push(*c);
+        }
+        for cords in layers.values_mut() {
+            cords.sort_by_key(|c| (c.x, if c.x % 2 == 0 { c.z } else { -c.z }));
+        }
+        layers
+    }
+
+    pub fn solve(&mut self, sys: &mut System, deadline: &Deadline) -> Result<()> {
+        if self.model.targets.iter().any(|c| c.x == 0 && c.z == 0) {
+            warn!("Sweep: the column at x = 0, z = 0 is not empty");
+            return Err(NanoBotError.into());
+        }
+        let assemble = match self.model.id {
+            ModelId::Assemble(_) => true,
+            ModelId::Disassemble(_) => false,
+        };
+        let mut layers = self.layers().into_iter().collect::<Vec<_>>();
+        if !assemble {
+            // Void from the top, so that the bot always moves in voided layers.
+            layers.reverse();
+        }
+        let below = Near(CordDiff::new(0, -1, 0));
+
+        Sweep::execute(sys, Cmd::Flip)?;
+        for (y, cords) in layers {
+            if deadline.is_expired() {
+                return Err(Timeout.into());
+            }
+            // Change the layer above the column which the bot is on, which
+            // is empty both before and after this layer.
+            let pos = sys.current_bot().pos;
+            Sweep::move_to(sys, Cord::new(pos.x, y + 1, pos.z))?;
+            for c in cords {
+                Sweep::move_to(sys, Cord::new(c.x, y + 1, c.z))?;
+                Sweep::execute(
+                    sys,
+                    if assemble {
+                        Cmd::Fill(below)
+                    } else {
+                        Cmd::Void(below)
+                    },
+                )?;
+            }
+        }
+        let pos = sys.current_bot().pos;
+        Sweep::move_to(sys, Cord::new(0, pos.y, 0))?;
+        Sweep::move_to(sys, Cord::new(0, 0, 0))?;
+        Sweep::execute(sys, Cmd::Flip)?;
+        Sweep::execute(sys, Cmd::Halt)
+    }
+}
@@ -1,10 +1,11 @@
-// use std;
+use failure;
+use std;
 
 use super::prelude::*;
 
 pub type BotId = u64;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Cmd {
     Halt,
     Wait,
@@ -61,6 +62,74 @@ impl From<Cmd> for Vec<u8> {
     }
 }
 
+impl Cmd {
+    pub fn name(&self) -> &'static str {
+        use self::Cmd::*;
+        match self {
+            Halt => "Halt",
+            Wait => "Wait",
+            Flip => "Flip",
+            SMove(_) => "SMove",
+            LMove(..) => "LMove",
+            Fission(..) => "Fission",
+            Fill(_) => "Fill",
+            Void(_) => "Void",
+            FusionP(_) => "FusionP",
+            FusionS(_) => "FusionS",
+        }
+    }
+
+    /// Decodes one command from the head of `bytes`. Returns the command and
+    /// the number of bytes consumed.
+    pub fn decode(bytes: &[u8]) -> Result<(Cmd, usize)> {
+        use self::Cmd::*;
+        let b0 = *bytes.first().ok_or_err()?;
+        let second = || bytes.get(1).cloned().ok_or_err();
+        let cmd = match b0 {
+            0b_1111_1111 => (Halt, 1),
+            0b_1111_1110 => (Wait, 1),
+            0b_1111_1101 => (Flip, 1),
+            _ => match b0 & 0b_111 {
+                0b_111 => (FusionP(Near::decode(b0 >> 3)?), 1),
+                0b_110 => (FusionS(Near::decode(b0 >> 3)?), 1),
+                0b_101 => (Fission(Near::decode(b0 >> 3)?, second()? as usize), 2),
+                0b_011 => (Fill(Near::decode(b0 >> 3)?), 1),
+                0b_010 => (Void(Near::decode(b0 >> 3)?), 1),
+                _ => match b0 & 0b_1111 {
+                    0b_0100 => {
+                        let i = second()?;
+                        (
+                            SMove(LongLinear::decode((b0 >> 4) & 0b_11, i & 0b_0001_1111)?),
+                            2,
+                        )
+                    }
+                    0b_1100 => {
+                        let i = second()?;
+                        (
+                            LMove(
+                                ShortLinear::decode((b0 >> 4) & 0b_11, i & 0b_1111)?,
+                                ShortLinear::decode((b0 >> 6) & 0b_11, i >> 4)?,
+                            ),
+                            2,
+                        )
+                    }
+                    _ => return Err(NanoBotError.into()),
+                },
+            },
+        };
+        Ok(cmd)
+    }
+}
+
+fn decode_linear(a: u8, d: i32) -> Result<CordDiff> {
+    match a {
+        0b_01 => Ok(CordDiff::new(d, 0, 0)),
+        0b_10 => Ok(CordDiff::new(0, d, 0)),
+        0b_11 => Ok(CordDiff::new(0, 0, d)),
+        _ => Err(NanoBotError.into()),
+    }
+}
+
 pub struct CordDiffBits {
     pub a: u8,
     pub i: u8,
@@ -89,6 +158,28 @@ impl ShortLinear {
     }
 }
 
+impl ShortLinear {
+    pub fn decode(a: u8, i: u8) -> Result<ShortLinear> {
+        let d = decode_linear(a, i as i32 - 5)?;
+        if d.is_short_linear() {
+            Ok(ShortLinear(d))
+        } else {
+            Err(NanoBotError.into())
+        }
+    }
+}
+
+impl LongLinear {
+    pub fn decode(a: u8, i: u8) -> Result<LongLinear> {
+        let d = decode_linear(a, i as i32 - 15)?;
+        if d.is_long_linear() {
+            Ok(LongLinear(d))
+        } else {
+            Err(NanoBotError.into())
+        }
+    }
+}
+
 impl LongLinear {
     pub fn encode(&self) -> CordDiffBits {
         let cord = &self.0;
@@ -118,6 +209,126 @@ impl Near {
         let c = &self.0;
         ((c.dx + 1) * 9 + (c.dy + 1) * 3 + (c.dz + 1)) as u8
     }
+
+    pub fn decode(nd: u8) -> Result<Near> {
+        let nd = nd as i32;
+        let d = CordDiff::new(nd / 9 - 1, (nd / 3) % 3 - 1, nd % 3 - 1);
+        if nd < 27 && d.is_near() {
+            Ok(Near(d))
+        } else {
+            Err(NanoBotError.into())
+        }
+    }
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "Invalid command: {}", _0)]
+pub struct InvalidCmd(pub String);
+
+// "x+12", "z-3".
+fn format_linear(d: CordDiff) -> String {
+    let (axis, len) = if d.dx != 0 {
+        ('x', d.dx)
+    } else if d.dy != 0 {
+        ('y', d.dy)
+    } else {
+        ('z', d.dz)
+    };
+    format!("{}{:+}", axis, len)
+}
+
+fn parse_linear(s: &str, max_len: i32) -> Result<CordDiff> {
+    let invalid = || InvalidCmd(format!("linear difference: {}", s));
+    let mut chars = s.chars();
+    let axis = chars.next().ok_or_else(invalid)?;
+    let rest = chars.as_str();
+    if !rest.starts_with('+') && !rest.starts_with('-') {
+        return Err(invalid().into());
+    }
+    let len: i32 = rest.parse().map_err(|_| invalid())?;
+    if len == 0 || len.abs() > max_len {
+        return Err(invalid().into());
+    }
+    match axis {
+        'x' => Ok(CordDiff::new(len, 0, 0)),
+        'y' => Ok(CordDiff::new(0, len, 0)),
+        'z' => Ok(CordDiff::new(0, 0, len)),
+        _ => Err(invalid().into()),
+    }
+}
+
+// "0,-1,0".
+fn parse_near(s: &str) -> Result<Near> {
+    let invalid = || InvalidCmd(format!("near difference: {}", s));
+    let ds = s
+        .split(',')
+        .map(|d| d.parse::<i32>().map_err(|_| invalid()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if ds.len() != 3 {
+        return Err(invalid().into());
+    }
+    let d = CordDiff::new(ds[0], ds[1], ds[2]);
+    if !d.is_near() {
+        return Err(invalid().into());
+    }
+    Ok(Near(d))
+}
+
+// The text format of a command, e.g. "SMove x+12", "LMove x+2 z-3",
+// "Fill 0,-1,0", "Fission 1,0,0 5". See `Trace` for the whole format.
+impl std::fmt::Display for Cmd {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use self::Cmd::*;
+        let near = |nd: &Near| format!("{},{},{}", nd.0.dx, nd.0.dy, nd.0.dz);
+        match self {
+            Halt => write!(f, "Halt"),
+            Wait => write!(f, "Wait"),
+            Flip => write!(f, "Flip"),
+            SMove(lld) => write!(f, "SMove {}", format_linear(lld.0)),
+            LMove(sld1, sld2) => write!(
+                f,
+                "LMove {} {}",
+                format_linear(sld1.0),
+                format_linear(sld2.0)
+            ),
+            Fission(nd, m) => write!(f, "Fission {} {}", near(nd), m),
+            Fill(nd) => write!(f, "Fill {}", near(nd)),
+            Void(nd) => write!(f, "Void {}", near(nd)),
+            FusionP(nd) => write!(f, "FusionP {}", near(nd)),
+            FusionS(nd) => write!(f, "FusionS {}", near(nd)),
+        }
+    }
+}
+
+impl std::str::FromStr for Cmd {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Cmd> {
+        use self::Cmd::*;
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let cmd = match words.as_slice() {
+            ["Halt"] => Halt,
+            ["Wait"] => Wait,
+            ["Flip"] => Flip,
+            ["SMove", lld] => SMove(LongLinear(parse_linear(lld, 15)?)),
+            ["LMove", sld1, sld2] => LMove(
+                ShortLinear(parse_linear(sld1, 5)?),
+                ShortLinear(parse_linear(sld2, 5)?),
+            ),
+            ["Fission", nd, m] => {
+                let m = m
+                    .parse::<u8>()
+                    .map_err(|_| InvalidCmd(format!("seeds: {}", m)))?;
+                Fission(parse_near(nd)?, m as usize)
+            }
+            ["Fill", nd] => Fill(parse_near(nd)?),
+            ["Void", nd] => Void(parse_near(nd)?),
+            ["FusionP", nd] => FusionP(parse_near(nd)?),
+            ["FusionS", nd] => FusionS(parse_near(nd)?),
+            _ => return Err(InvalidCmd(s.to_string()).into()),
+        };
+        Ok(cmd)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -179,14 +390,62 @@ impl Bot {
 mod test {
     use super::*;
 
+    #[test]
+    fn cmd_text_test() {
+        use self::Cmd::*;
+
+        let cmds = vec![
+            (Halt, "Halt"),
+            (Wait, "Wait"),
+            (Flip, "Flip"),
+            (SMove(LongLinear(CordDiff::new(12, 0, 0))), "SMove x+12"),
+            (SMove(LongLinear(CordDiff::new(0, 0, -4))), "SMove z-4"),
+            (
+                LMove(
+                    ShortLinear(CordDiff::new(3, 0, 0)),
+                    ShortLinear(CordDiff::new(0, -5, 0)),
+                ),
+                "LMove x+3 y-5",
+            ),
+            (Fission(Near(CordDiff::new(0, 0, 1)), 5), "Fission 0,0,1 5"),
+            (Fill(Near(CordDiff::new(0, -1, 0))), "Fill 0,-1,0"),
+            (Void(Near(CordDiff::new(1, 0, 1))), "Void 1,0,1"),
+            (FusionP(Near(CordDiff::new(-1, 1, 0))), "FusionP -1,1,0"),
+            (FusionS(Near(CordDiff::new(1, -1, 0))), "FusionS 1,-1,0"),
+        ];
+        for (cmd, text) in cmds {
+            assert_eq!(cmd.to_string(), text);
+            assert_eq!(text.parse::<Cmd>().unwrap(), cmd);
+        }
+        assert_eq!(
+            "  SMove   y+1 ".parse::<Cmd>().unwrap(),
+            SMove(LongLinear(CordDiff::new(0, 1, 0)))
+        );
+        for text in &[
+            "Halt 1",
+            "SMove x+16",
+            "SMove x+0",
+            "SMove x12",
+            "SMove w+1",
+            "LMove x+6 y+1",
+            "Fill 1,1,1",
+            "Fill 0,0,0",
+            "Fill 0,1",
+            "Fission 0,1,0 256",
+            "Move x+1",
+        ] {
+            assert!(text.parse::<Cmd>().is_err(), "{}", text);
+        }
+    }
+
     #[test]
     fn cmd_encode_test() {
         use self::Cmd::*;
 
         let bytes: Vec<u8> = Halt.into();
-        assert_eq!(bytes, vec![255 as u8]);
+        assert_eq!(bytes, vec![255_u8]);
 
-        assert_eq!(Vec::<u8>::from(Halt), vec![255 as u8]);
+        assert_eq!(Vec::<u8>::from(Halt), vec![255_u8]);
 
         assert_eq!(
             Vec::<u8>::from(SMove(LongLinear(CordDiff::new(12, 0, 0)))),
@@ -238,4 +497,37 @@ mod test {
         );
     }
 
+    #[test]
+    fn cmd_decode_test() {
+        use self::Cmd::*;
+
+        let cmds = vec![
+            Halt,
+            Wait,
+            Flip,
+            SMove(LongLinear(CordDiff::new(12, 0, 0))),
+            SMove(LongLinear(CordDiff::new(0, 0, -4))),
+            LMove(
+                ShortLinear(CordDiff::new(3, 0, 0)),
+                ShortLinear(CordDiff::new(0, -5, 0)),
+            ),
+            Fission(Near(CordDiff::new(0, 0, 1)), 5),
+            FusionP(Near(CordDiff::new(-1, 1, 0))),
+            FusionS(Near(CordDiff::new(1, -1, 0))),
+            Fill(Near(CordDiff::new(0, -1, 0))),
+            Void(Near(CordDiff::new(1, 0, 1))),
+        ];
+        for cmd in cmds {
+            let bytes = Vec::<u8>::from(cmd);
+            let (decoded, len) = Cmd::decode(&bytes).unwrap();
+            assert_eq!(decoded, cmd);
+            assert_eq!(len, bytes.len());
+        }
+
+        // Near(1, 1, 1) is not a near coordinate difference.
+        assert!(Cmd::decode(&[(26 << 3) | 0b_011]).is_err());
+        // SMove without its second byte.
+        assert!(Cmd::decode(&[0b_00010100]).is_err());
+    }
+
 }
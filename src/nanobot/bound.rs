@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+
+use super::prelude::*;
+use super::problem::*;
+
+const MAX_BOTS: i64 = 40;
+
+// The minimum number of time steps in which `ops` voxels can be filled or
+// voided, one voxel per bot per time step. The number of bots can at most
+// double in each time step, up to 40.
+fn min_time_steps(ops: i64) -> i64 {
+    let mut bots = 1;
+    let mut done = 0;
+    let mut steps = 0;
+    while done < ops {
+        done += bots;
+        bots = (bots * 2).min(MAX_BOTS);
+        steps += 1;
+    }
+    steps
+}
+
+/// A lower bound of the energy of any trace which turns `src` into `tgt`,
+/// using the commands which `Cmd` supports.
+///
+/// - Every voxel in `tgt` but not in `src` is filled (12 each), and every
+///   voxel in `src` but not in `tgt` is voided (-12 each).
+/// - A bot fills or voids at most one voxel per time step, and the number of
+///   bots at most doubles per time step, up to 40. The last time step is Halt.
+/// - Every time step costs at least Low harmonics (3 * R^3), and 20 per bot.
+///   Each fill, void or Halt takes one bot for one time step.
+/// - Moves cost nothing here, and Fission and Fusion cancel out.
+pub fn energy_lower_bound(r: usize, src: &HashSet<Cord>, tgt: &HashSet<Cord>) -> i64 {
+    let fills = tgt.difference(src).count() as i64;
+    let voids = src.difference(tgt).count() as i64;
+    let ops = fills + voids;
+    let time_steps = min_time_steps(ops) + 1;
+    let r = r as i64;
+    3 * r * r * r * time_steps + 20 * time_steps.max(ops + 1) + 12 * fills - 12 * voids
+}
+
+impl Problem {
+    pub fn energy_lower_bound(&self) -> i64 {
+        let empty = HashSet::new();
+        energy_lower_bound(
+            self.r(),
+            self.src.as_ref().map_or(&empty, |m| &m.targets),
+            self.tgt.as_ref().map_or(&empty, |m| &m.targets),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn min_time_steps_test() {
+        assert_eq!(min_time_steps(0), 0);
+        assert_eq!(min_time_steps(1), 1);
+        assert_eq!(min_time_steps(3), 2);
+        // 1 + 2 + 4 + 8 + 16 + 32 = 63
+        assert_eq!(min_time_steps(63), 6);
+        assert_eq!(min_time_steps(64), 7);
+        assert_eq!(min_time_steps(63 + 40 * 10), 16);
+    }
+
+    #[test]
+    fn energy_lower_bound_test() {
+        let empty = HashSet::new();
+        let one = vec![Cord::new(1, 0, 1)].into_iter().collect();
+        assert_eq!(energy_lower_bound(3, &empty, &empty), 3 * 27 + 20);
+        assert_eq!(
+            energy_lower_bound(3, &empty, &one),
+            2 * 3 * 27 + 20 * 2 + 12
+        );
+        assert_eq!(
+            energy_lower_bound(3, &one, &empty),
+            2 * 3 * 27 + 20 * 2 - 12
+        );
+        assert_eq!(energy_lower_bound(3, &one, &one), 3 * 27 + 20);
+    }
+}
@@ -0,0 +1,129 @@
+// Results of ci, cached by the model files and the strategy.
+//
+// A result is stored as `<model>/<key>.json` and `<model>/<key>.nbt` in the
+// cache directory as soon as it is solved, so an interrupted run resumes
+// where it stopped. The key changes when a model file, the strategy or the
+// version of its AI changes.
+
+use serde_json;
+
+use std;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::ai::*;
+use super::ledger::sha256_hex;
+use super::prelude::*;
+use super::run::RunResult;
+use super::trace::*;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    model_name: String,
+    strategy: Strategy,
+    version: u32,
+    energy: Option<i64>,
+    #[serde(default)]
+    time_steps: Option<usize>,
+    solve_millis: u64,
+}
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl AsRef<Path>) -> Cache {
+        Cache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, model_name: &str, model_sha256: &str, strategy: Strategy) -> PathBuf {
+        let key = sha256_hex(
+            format!("{}\n{}\n{}", model_sha256, strategy, strategy.ai.version()).as_bytes(),
+        );
+        self.dir.join(model_name).join(&key[..16])
+    }
+
+    pub fn get(
+        &self,
+        model_name: &str,
+        model_sha256: &str,
+        strategy: Strategy,
+    ) -> Result<Option<RunResult>> {
+        let path = self.path(model_name, model_sha256, strategy);
+        let json_path = path.with_extension("json");
+        if !json_path.exists() {
+            return Ok(None);
+        }
+        let entry: CacheEntry = serde_json::from_str(&std::fs::read_to_string(json_path)?)?;
+        Ok(Some(RunResult {
+            problem_name: entry.model_name,
+            strategy: entry.strategy,
+            energy: entry.energy,
+            trace: Trace::read(path.with_extension("nbt"))?,
+            elapsed: Duration::from_millis(entry.solve_millis),
+            time_steps: entry.time_steps,
+            timed_out: false,
+        }))
+    }
+
+    pub fn put(&self, model_sha256: &str, run_result: &RunResult) -> Result<()> {
+        let path = self.path(&run_result.problem_name, model_sha256, run_result.strategy);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        run_result.trace.write_to(path.with_extension("nbt"))?;
+        let entry = CacheEntry {
+            model_name: run_result.problem_name.clone(),
+            strategy: run_result.strategy,
+            version: run_result.strategy.ai.version(),
+            energy: run_result.energy,
+            time_steps: run_result.time_steps,
+            solve_millis: run_result.elapsed.as_millis() as u64,
+        };
+        // The json is written last, so that a half-written entry is not used.
+        std::fs::write(path.with_extension("json"), serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nanobot::deadline::*;
+    use nanobot::model::*;
+    use nanobot::problem::*;
+    use nanobot::run::solve;
+
+    #[test]
+    fn cache_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-cache-{}", std::process::id()));
+        let model_dir = dir.join("model");
+        std::fs::create_dir_all(&model_dir).unwrap();
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let model = Model::from_cords(ModelId::Assemble(None), 6, targets);
+        std::fs::write(model_dir.join("FA001_tgt.mdl"), model.encode()).unwrap();
+        let files = &discover_problems(&model_dir).unwrap()[0];
+        let sha256 = files.sha256().unwrap();
+
+        let cache = Cache::new(dir.join("cache"));
+        let strategy = Ai::Many(2).into();
+        assert!(cache.get("FA001", &sha256, strategy).unwrap().is_none());
+        let run_result = solve(&files.read().unwrap(), strategy, &Deadline::none()).unwrap();
+        cache.put(&sha256, &run_result).unwrap();
+
+        let cached = cache.get("FA001", &sha256, strategy).unwrap().unwrap();
+        assert_eq!(cached.energy, run_result.energy);
+        assert_eq!(cached.trace.cmds, run_result.trace.cmds);
+        assert!(cache
+            .get("FA001", &sha256, Ai::Many(3).into())
+            .unwrap()
+            .is_none());
+
+        // The model changes.
+        std::fs::write(model_dir.join("FA001_tgt.mdl"), b"").unwrap();
+        let sha256 = files.sha256().unwrap();
+        assert!(cache.get("FA001", &sha256, strategy).unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,705 @@
+// Shares a ci run among machines.
+//
+// The coordinator listens on TCP, and workers connect to it. Each side sends
+// one JSON message per line. A worker asks for a job, i.e. a model and a
+// strategy, fetches the model files unless it has them already, solves it and
+// sends back the trace. The coordinator verifies every trace itself. A job of
+// a worker which disconnects or stops responding is handed to another one, up
+// to `MAX_ATTEMPTS` times.
+
+use serde;
+use serde_json;
+
+use std;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::ai::*;
+use super::cache::*;
+use super::deadline::*;
+use super::model::*;
+use super::prelude::*;
+use super::problem::*;
+use super::progress::*;
+use super::run::{cache_result, solve, verified, Portfolio, RunResult};
+use super::trace::*;
+use rayon;
+
+#[derive(Fail, Debug)]
+#[fail(display = "Protocol error: {}", _0)]
+pub struct ProtocolError(pub String);
+
+#[derive(Fail, Debug)]
+#[fail(display = "Worker thread panicked")]
+pub struct WorkerPanic;
+
+// How long a message may take on the wire, and how long a worker may take
+// beyond the time budget of its job.
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+// A job fails after it is handed out this many times.
+const MAX_ATTEMPTS: usize = 3;
+
+// Bytes are sent in hex.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+enum Request {
+    Next,
+    /// The model files of the current job.
+    Fetch,
+    Done {
+        job_id: u64,
+        energy: Option<i64>,
+        trace: String,
+        elapsed_millis: u64,
+        #[serde(default)]
+        timed_out: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+enum Response {
+    Job {
+        job_id: u64,
+        model_name: String,
+        /// The SHA-256 of the model files, by which workers keep models.
+        sha256: String,
+        strategy: Strategy,
+        timeout_millis: Option<u64>,
+    },
+    Model {
+        src: Option<String>,
+        tgt: Option<String>,
+    },
+    /// No more jobs.
+    Finished,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return Err(ProtocolError("odd hex length".to_string()).into());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&s[i..i + 2], 16)
+                .map_err(|_| ProtocolError(format!("invalid hex: {}", &s[i..i + 2])).into())
+        })
+        .collect()
+}
+
+fn read_message<T: serde::de::DeserializeOwned>(
+    reader: &mut BufReader<TcpStream>,
+) -> Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+fn write_message<T: serde::Serialize>(writer: &mut TcpStream, message: &T) -> Result<()> {
+    writeln!(writer, "{}", serde_json::to_string(message)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+struct Job {
+    id: u64,
+    index: usize,
+    files: ProblemFiles,
+    sha256: String,
+    strategy: Strategy,
+    deadline: Deadline,
+    /// The number of times it was handed to a worker.
+    attempts: usize,
+    reply: mpsc::Sender<(usize, RunResult)>,
+}
+
+impl Job {
+    fn fail(self, timed_out: bool) {
+        let run_result = RunResult {
+            problem_name: self.files.name.clone(),
+            strategy: self.strategy,
+            energy: None,
+            trace: Trace { cmds: vec![] },
+            elapsed: Duration::from_secs(0),
+            time_steps: None,
+            timed_out,
+        };
+        let _ = self.reply.send((self.index, run_result));
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    jobs: VecDeque<Job>,
+    next_id: u64,
+    finished: bool,
+}
+
+#[derive(Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    available: Condvar,
+}
+
+impl Queue {
+    fn push(&self, job: Job) {
+        self.state.lock().unwrap().jobs.push_back(job);
+        self.available.notify_one();
+    }
+
+    // A job which a worker gave up goes first.
+    fn requeue(&self, job: Job) {
+        self.state.lock().unwrap().jobs.push_front(job);
+        self.available.notify_one();
+    }
+
+    fn next_id(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        state.next_id
+    }
+
+    /// Waits for a job. None when finished.
+    fn pop(&self) -> Option<Job> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                return Some(job);
+            }
+            if state.finished {
+                return None;
+            }
+            state = self.available.wait(state).unwrap();
+        }
+    }
+
+    /// Fails the queued jobs whose time budget expired.
+    fn fail_expired(&self) {
+        let expired = {
+            let mut state = self.state.lock().unwrap();
+            let (expired, jobs): (Vec<_>, Vec<_>) = state
+                .jobs
+                .drain(..)
+                .partition(|job| job.deadline.is_expired());
+            state.jobs = jobs.into();
+            expired
+        };
+        for job in expired {
+            job.fail(true);
+        }
+    }
+
+    fn finish(&self) {
+        self.state.lock().unwrap().finished = true;
+        self.available.notify_all();
+    }
+}
+
+pub struct Coordinator {
+    addr: SocketAddr,
+    queue: Arc<Queue>,
+}
+
+impl Coordinator {
+    /// Listens on `addr`, e.g. "0.0.0.0:7878". Port 0 picks a free one.
+    pub fn bind(addr: &str) -> Result<Coordinator> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let queue = Arc::new(Queue::default());
+        let q = queue.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let q = q.clone();
+                        thread::spawn(move || serve(stream, &q));
+                    }
+                    Err(e) => warn!("Coordinator: accept: {}", e),
+                }
+            }
+        });
+        Ok(Coordinator { addr, queue })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Solves `problem` with every strategy on workers, and verifies and caches
+    /// the traces. Jobs count as running in `progress` while they are queued.
+    /// Jobs which are still queued when `deadline` expires fail, even if no
+    /// worker is connected. Jobs which workers have are waited for, since
+    /// workers get the time budget too.
+    pub fn solve_portfolio(
+        &self,
+        files: &ProblemFiles,
+        problem: &Problem,
+        strategies: &[Strategy],
+        deadline: &Deadline,
+        progress: &Progress,
+        cache: Option<&Cache>,
+    ) -> Result<Portfolio> {
+        let sha256 = files.sha256()?;
+        let (reply, results) = mpsc::channel();
+        let mut tasks = vec![];
+        for (index, strategy) in strategies.iter().enumerate() {
+            tasks.push(Some(progress.start(&files.name, *strategy)));
+            self.queue.push(Job {
+                id: self.queue.next_id(),
+                index,
+                files: files.clone(),
+                sha256: sha256.clone(),
+                strategy: *strategy,
+                deadline: deadline.clone(),
+                attempts: 0,
+                reply: reply.clone(),
+            });
+        }
+        drop(reply);
+        let lost = || ProtocolError(format!("jobs lost: {}", files.name));
+        let mut run_results = vec![];
+        while run_results.len() < strategies.len() {
+            let received = match deadline.remaining() {
+                Some(remaining) => {
+                    // Jobs may be requeued after the deadline, so they are
+                    // checked now and then.
+                    let received = results.recv_timeout(remaining.max(Duration::from_millis(100)));
+                    if deadline.is_expired() {
+                        self.queue.fail_expired();
+                    }
+                    match received {
+                        Ok(received) => Some(received),
+                        Err(mpsc::RecvTimeoutError::Timeout) => None,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return Err(lost().into()),
+                    }
+                }
+                None => Some(results.recv().map_err(|_| lost())?),
+            };
+            if let Some((index, run_result)) = received {
+                let run_result = verified(problem, run_result);
+                cache_result(cache, &sha256, &run_result);
+                progress.finish(tasks[index].take().unwrap(), &run_result)?;
+                run_results.push((index, run_result));
+            }
+        }
+        // In the order of `strategies`, as `run::solve_portfolio`.
+        run_results.sort_by_key(|&(index, _)| index);
+        Ok(Portfolio {
+            results: run_results.into_iter().map(|(_, r)| r).collect(),
+        })
+    }
+
+    /// Tells workers that there are no more jobs.
+    pub fn finish(&self) {
+        self.queue.finish();
+    }
+}
+
+impl Drop for Coordinator {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+fn serve(stream: TcpStream, queue: &Queue) {
+    let peer = stream
+        .peer_addr()
+        .map_or("unknown".to_string(), |a| a.to_string());
+    info!("Coordinator: worker connected: {}", peer);
+    let mut current = None;
+    if let Err(e) = serve_jobs(stream, queue, &mut current) {
+        warn!("Coordinator: worker: {}: {}", peer, e);
+    }
+    if let Some(job) = current.take() {
+        if job.attempts < MAX_ATTEMPTS {
+            info!("Coordinator: requeued: {} {}", job.files.name, job.strategy);
+            queue.requeue(job);
+        } else {
+            warn!(
+                "Coordinator: failed after {} attempts: {} {}",
+                job.attempts, job.files.name, job.strategy
+            );
+            job.fail(false);
+        }
+    }
+    info!("Coordinator: worker disconnected: {}", peer);
+}
+
+fn serve_jobs(stream: TcpStream, queue: &Queue, current: &mut Option<Job>) -> Result<()> {
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    while let Some(request) = read_message(&mut reader)? {
+        match request {
+            Request::Next => {
+                if current.is_some() {
+                    // `serve` hands the job to another worker.
+                    return Err(ProtocolError("Next with a job".to_string()).into());
+                }
+                // A job whose time budget expired while queued fails here.
+                let job = loop {
+                    match queue.pop() {
+                        Some(job) if job.deadline.is_expired() => job.fail(true),
+                        job => break job,
+                    }
+                };
+                let mut job = match job {
+                    Some(job) => job,
+                    None => {
+                        write_message(&mut writer, &Response::Finished)?;
+                        return Ok(());
+                    }
+                };
+                write_message(
+                    &mut writer,
+                    &Response::Job {
+                        job_id: job.id,
+                        model_name: job.files.name.clone(),
+                        sha256: job.sha256.clone(),
+                        strategy: job.strategy,
+                        timeout_millis: job.deadline.remaining().map(|d| d.as_millis() as u64),
+                    },
+                )?;
+                // A worker which does not finish the job in time is given up,
+                // and `serve` requeues the job.
+                let timeout = job.deadline.remaining().map(|d| d + IO_TIMEOUT);
+                writer.set_read_timeout(timeout)?;
+                job.attempts += 1;
+                *current = Some(job);
+            }
+            Request::Fetch => {
+                let job = current
+                    .as_ref()
+                    .ok_or_else(|| ProtocolError("Fetch without a job".to_string()))?;
+                let read_hex = |path: &Option<std::path::PathBuf>| -> Result<Option<String>> {
+                    match path {
+                        Some(path) => Ok(Some(to_hex(&std::fs::read(path)?))),
+                        None => Ok(None),
+                    }
+                };
+                let src = read_hex(&job.files.src)?;
+                let tgt = read_hex(&job.files.tgt)?;
+                write_message(&mut writer, &Response::Model { src, tgt })?;
+            }
+            Request::Done {
+                job_id,
+                energy,
+                trace,
+                elapsed_millis,
+                timed_out,
+            } => {
+                let job = match current.take() {
+                    Some(job) if job.id == job_id => job,
+                    job => {
+                        *current = job;
+                        return Err(ProtocolError(format!("unknown job: {}", job_id)).into());
+                    }
+                };
+                let trace = match from_hex(&trace).and_then(|bytes| Trace::decode(&bytes)) {
+                    Ok(trace) => trace,
+                    Err(e) => {
+                        // Hand it to another worker.
+                        *current = Some(job);
+                        return Err(e);
+                    }
+                };
+                let run_result = RunResult {
+                    problem_name: job.files.name.clone(),
+                    strategy: job.strategy,
+                    energy,
+                    trace,
+                    elapsed: Duration::from_millis(elapsed_millis),
+                    time_steps: None,
+                    timed_out,
+                };
+                let _ = job.reply.send((job.index, run_result));
+                writer.set_read_timeout(None)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn decode_problem(name: &str, src: Option<String>, tgt: Option<String>) -> Result<Problem> {
+    let src = match src {
+        Some(hex) => Some(Model::decode(
+            ModelId::Disassemble(Some(name.to_string())),
+            &from_hex(&hex)?,
+        )?),
+        None => None,
+    };
+    let tgt = match tgt {
+        Some(hex) => Some(Model::decode(
+            ModelId::Assemble(Some(name.to_string())),
+            &from_hex(&hex)?,
+        )?),
+        None => None,
+    };
+    if src.is_none() && tgt.is_none() {
+        return Err(ProtocolError(format!("no model files: {}", name)).into());
+    }
+    Ok(Problem::new(name.to_string(), src, tgt))
+}
+
+// Solves jobs until the coordinator has no more. Returns the number of jobs.
+fn work_loop(addr: SocketAddr) -> Result<usize> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut problems = HashMap::new();
+    let mut count = 0;
+    loop {
+        write_message(&mut writer, &Request::Next)?;
+        let response = match read_message(&mut reader)? {
+            Some(response) => response,
+            // The coordinator may exit without saying Finished.
+            None => return Ok(count),
+        };
+        let (job_id, model_name, sha256, strategy, timeout_millis) = match response {
+            Response::Finished => return Ok(count),
+            Response::Job {
+                job_id,
+                model_name,
+                sha256,
+                strategy,
+                timeout_millis,
+            } => (job_id, model_name, sha256, strategy, timeout_millis),
+            Response::Model { .. } => {
+                return Err(ProtocolError("unexpected Model".to_string()).into());
+            }
+        };
+        if !problems.contains_key(&sha256) {
+            write_message(&mut writer, &Request::Fetch)?;
+            // Next waits for a job, but Fetch is answered at once.
+            writer.set_read_timeout(Some(IO_TIMEOUT))?;
+            let response = read_message(&mut reader)?;
+            writer.set_read_timeout(None)?;
+            let problem = match response {
+                Some(Response::Model { src, tgt }) => decode_problem(&model_name, src, tgt)?,
+                _ => return Err(ProtocolError("expected Model".to_string()).into()),
+            };
+            problems.insert(sha256.clone(), problem);
+        }
+        info!("Worker: model: {}, strategy: {}", model_name, strategy);
+        let deadline = Deadline::after(timeout_millis.map(Duration::from_millis));
+        let run_result = solve(&problems[&sha256], strategy, &deadline)?;
+        write_message(
+            &mut writer,
+            &Request::Done {
+                job_id,
+                energy: run_result.energy,
+                trace: to_hex(&run_result.trace.encode()),
+                elapsed_millis: run_result.elapsed.as_millis() as u64,
+                timed_out: run_result.timed_out,
+            },
+        )?;
+        count += 1;
+    }
+}
+
+/// Connects `jobs` workers, one per CPU by default, to the coordinator at
+/// `addr`, and solves jobs until it has no more. Returns the number of jobs
+/// solved.
+pub fn work(addr: &str, jobs: Option<usize>) -> Result<usize> {
+    let addr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| ProtocolError(format!("invalid address: {}", addr)))?;
+    let jobs = jobs.unwrap_or_else(rayon::current_num_threads).max(1);
+    let handles = (0..jobs)
+        .map(|_| thread::spawn(move || work_loop(addr)))
+        .collect::<Vec<_>>();
+    let mut count = 0;
+    for handle in handles {
+        count += handle.join().map_err(|_| WorkerPanic)??;
+    }
+    info!("Worker: solved {} jobs", count);
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex_test() {
+        assert_eq!(to_hex(&[0, 1, 0xab, 0xff]), "0001abff");
+        assert_eq!(from_hex("0001abff").unwrap(), vec![0, 1, 0xab, 0xff]);
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+    }
+
+    #[test]
+    fn cluster_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-cluster-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let model = Model::from_cords(ModelId::Assemble(None), 6, targets);
+        std::fs::write(dir.join("FA001_tgt.mdl"), model.encode()).unwrap();
+        let files = &discover_problems(&dir).unwrap()[0];
+        let problem = files.read().unwrap();
+
+        let coordinator = Coordinator::bind("127.0.0.1:0").unwrap();
+        let addr = coordinator.local_addr().to_string();
+        let worker = thread::spawn(move || work(&addr, Some(2)).unwrap());
+
+        let strategies = vec![Ai::Many(2).into(), Ai::Many(3).into(), Ai::Default.into()];
+        let progress = Progress::new(strategies.len(), None).unwrap();
+        let portfolio = coordinator
+            .solve_portfolio(
+                files,
+                &problem,
+                &strategies,
+                &Deadline::none(),
+                &progress,
+                None,
+            )
+            .unwrap();
+        coordinator.finish();
+        assert_eq!(worker.join().unwrap(), strategies.len());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(portfolio.results.len(), strategies.len());
+        for (run_result, strategy) in portfolio.results.iter().zip(&strategies) {
+            assert_eq!(run_result.strategy, *strategy);
+            let local = solve(&problem, *strategy, &Deadline::none()).unwrap();
+            assert_eq!(run_result.energy, local.energy);
+            assert_eq!(run_result.trace.cmds, local.trace.cmds);
+            assert!(run_result.time_steps.is_some());
+        }
+    }
+
+    #[test]
+    fn no_worker_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-no-worker-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let model = Model::from_cords(ModelId::Assemble(None), 6, targets);
+        std::fs::write(dir.join("FA001_tgt.mdl"), model.encode()).unwrap();
+        let files = &discover_problems(&dir).unwrap()[0];
+        let problem = files.read().unwrap();
+
+        let coordinator = Coordinator::bind("127.0.0.1:0").unwrap();
+        let strategies = vec![Ai::Many(2).into(), Ai::Many(3).into()];
+        let progress = Progress::new(strategies.len(), None).unwrap();
+        let deadline = Deadline::after(Some(Duration::from_millis(200)));
+        let portfolio = coordinator
+            .solve_portfolio(files, &problem, &strategies, &deadline, &progress, None)
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(portfolio.results.len(), strategies.len());
+        assert!(portfolio.results.iter().all(|r| r.energy.is_none()));
+    }
+
+    #[test]
+    fn attempts_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-attempts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let model = Model::from_cords(ModelId::Assemble(None), 6, targets);
+        std::fs::write(dir.join("FA001_tgt.mdl"), model.encode()).unwrap();
+        let files = &discover_problems(&dir).unwrap()[0];
+        let problem = files.read().unwrap();
+
+        let coordinator = Coordinator::bind("127.0.0.1:0").unwrap();
+        let addr = coordinator.local_addr();
+        // Workers which take the job and disconnect.
+        let broken = thread::spawn(move || {
+            for _ in 0..MAX_ATTEMPTS {
+                let stream = TcpStream::connect(addr).unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut writer = stream;
+                write_message(&mut writer, &Request::Next).unwrap();
+                match read_message(&mut reader).unwrap() {
+                    Some(Response::Job { .. }) => {}
+                    response => panic!("{:?}", response),
+                }
+            }
+        });
+
+        let strategies = vec![Ai::Many(2).into()];
+        let progress = Progress::new(strategies.len(), None).unwrap();
+        let portfolio = coordinator
+            .solve_portfolio(
+                files,
+                &problem,
+                &strategies,
+                &Deadline::none(),
+                &progress,
+                None,
+            )
+            .unwrap();
+        broken.join().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(portfolio.results[0].energy, None);
+        assert!(!portfolio.results[0].timed_out);
+    }
+
+    #[test]
+    fn protocol_error_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-protocol-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let model = Model::from_cords(ModelId::Assemble(None), 6, targets);
+        std::fs::write(dir.join("FA001_tgt.mdl"), model.encode()).unwrap();
+        let files = &discover_problems(&dir).unwrap()[0];
+        let problem = files.read().unwrap();
+
+        let coordinator = Coordinator::bind("127.0.0.1:0").unwrap();
+        let addr = coordinator.local_addr();
+        // A broken worker takes the job, and sends Done for another one. The
+        // job goes to a good worker.
+        let broken = thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            write_message(&mut writer, &Request::Next).unwrap();
+            let job_id = match read_message(&mut reader).unwrap() {
+                Some(Response::Job { job_id, .. }) => job_id,
+                response => panic!("{:?}", response),
+            };
+            let done = Request::Done {
+                job_id: job_id + 1,
+                energy: None,
+                trace: String::new(),
+                elapsed_millis: 0,
+                timed_out: false,
+            };
+            write_message(&mut writer, &done).unwrap();
+            assert!(read_message::<Response>(&mut reader).unwrap().is_none());
+            let addr = addr.to_string();
+            work(&addr, Some(1)).unwrap()
+        });
+
+        let strategies = vec![Ai::Many(2).into()];
+        let progress = Progress::new(strategies.len(), None).unwrap();
+        let portfolio = coordinator
+            .solve_portfolio(
+                files,
+                &problem,
+                &strategies,
+                &Deadline::none(),
+                &progress,
+                None,
+            )
+            .unwrap();
+        coordinator.finish();
+        assert_eq!(broken.join().unwrap(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(portfolio.results[0].energy.is_some());
+    }
+}
@@ -0,0 +1,76 @@
+// Directories of the contest data.
+//
+// The contest directory has `trace/`, `submit/`, `report/` and `cache/`, and
+// usually `model/`. It is `--contest-dir`, `$ICFP2018_CONTEST_DIR`, or `contest/` in
+// this repository, in this order. Problems are read from `--problems-dir`,
+// `$ICFP2018_PROBLEMS_DIR`, or `model/` in the contest directory.
+
+use std;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref CONTEST_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+    static ref PROBLEMS_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+pub fn set_contest_dir(dir: PathBuf) {
+    *CONTEST_DIR.write().unwrap() = Some(dir);
+}
+
+pub fn set_problems_dir(dir: PathBuf) {
+    *PROBLEMS_DIR.write().unwrap() = Some(dir);
+}
+
+pub fn contest_dir() -> PathBuf {
+    if let Some(dir) = CONTEST_DIR.read().unwrap().as_ref() {
+        return dir.clone();
+    }
+    if let Some(dir) = std::env::var_os("ICFP2018_CONTEST_DIR") {
+        return dir.into();
+    }
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("contest");
+    path
+}
+
+pub fn problems_dir() -> PathBuf {
+    if let Some(dir) = PROBLEMS_DIR.read().unwrap().as_ref() {
+        return dir.clone();
+    }
+    if let Some(dir) = std::env::var_os("ICFP2018_PROBLEMS_DIR") {
+        return dir.into();
+    }
+    contest_dir().join("model")
+}
+
+pub fn trace_dir() -> PathBuf {
+    contest_dir().join("trace")
+}
+
+// Traces given by the organizers.
+pub fn default_trace_dir() -> PathBuf {
+    trace_dir().join("default")
+}
+
+// Default traces generated with `Ai::Default`, for problems which have no
+// trace from the organizers.
+pub fn sweep_trace_dir() -> PathBuf {
+    trace_dir().join("sweep")
+}
+
+pub fn submit_dir() -> PathBuf {
+    contest_dir().join("submit")
+}
+
+pub fn report_dir() -> PathBuf {
+    contest_dir().join("report")
+}
+
+pub fn default_telemetry_path() -> PathBuf {
+    report_dir().join("telemetry.jsonl")
+}
+
+pub fn cache_dir() -> PathBuf {
+    contest_dir().join("cache")
+}
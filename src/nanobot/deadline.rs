@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Fail, Debug)]
+#[fail(display = "Time budget expired")]
+pub struct Timeout;
+
+// A time budget shared by AIs. Clones share the cancellation flag, so
+// cancelling one cancels every derived deadline.
+#[derive(Clone, Default, Debug)]
+pub struct Deadline {
+    at: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Deadline {
+    pub fn none() -> Deadline {
+        Default::default()
+    }
+
+    pub fn after(duration: Option<Duration>) -> Deadline {
+        Deadline::none().within(duration)
+    }
+
+    /// Returns a deadline which expires after `duration` from now, or when
+    /// this deadline expires, whichever comes first.
+    pub fn within(&self, duration: Option<Duration>) -> Deadline {
+        let at = duration.map(|d| Instant::now() + d);
+        Deadline {
+            at: match (self.at, at) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            cancelled: self.cancelled.clone(),
+        }
+    }
+
+    /// Time left, or None if there is no time limit.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.at
+            .map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_expired(&self) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        match self.at {
+            Some(at) => Instant::now() >= at,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deadline_test() {
+        let deadline = Deadline::none();
+        assert!(!deadline.is_expired());
+        assert!(Deadline::after(Some(Duration::from_secs(0))).is_expired());
+
+        let child = deadline.within(Some(Duration::from_secs(3600)));
+        assert!(!child.is_expired());
+        assert!(child.within(Some(Duration::from_secs(0))).is_expired());
+
+        deadline.cancel();
+        assert!(child.is_expired());
+    }
+}
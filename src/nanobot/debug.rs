@@ -0,0 +1,449 @@
+// An interactive debugger of traces. It steps forward and back by commands or
+// time steps, and stops at breakpoints. Going back undoes the deltas recorded
+// by `Simulator::step`, so it costs as much as going forward.
+
+use failure;
+
+use std;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use super::bot::*;
+use super::prelude::*;
+use super::problem::*;
+use super::render::*;
+use super::simulator::{self, Delta, Simulator};
+use super::trace::*;
+
+const HELP: &str = "\
+s, step [n]          execute n commands
+b, back [n]          undo n commands
+n, next [n]          go to the beginning of the n-th next time step
+p, prev [n]          go back to the beginning of the n-th previous time step
+g, goto <t>          go to the beginning of time step t
+c, continue          go forward until a breakpoint
+rc, reverse          go back until a breakpoint
+break bot <x,y,z>    break when a bot gets to the cell
+break filled <x,y,z> break when the cell gets filled
+break energy <e>     break when the energy gets above e
+breaks               list breakpoints
+delete <i>           delete the i-th breakpoint
+i, info              print the state
+bots                 print bots and their next commands
+render [axis] [i]    render slices
+q, quit              quit
+An empty line repeats the last command.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    BotAt(Cord),
+    Filled(Cord),
+    EnergyAbove(i64),
+}
+
+fn parse_cord(s: &str) -> Result<Cord> {
+    let xs = s
+        .split(',')
+        .map(|x| x.trim().parse::<i32>())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| format_err!("Invalid cord: {}", s))?;
+    if xs.len() != 3 {
+        bail!("Invalid cord: {}", s);
+    }
+    Ok(Cord::new(xs[0], xs[1], xs[2]))
+}
+
+impl std::str::FromStr for Breakpoint {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Breakpoint> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["bot", c] => Ok(Breakpoint::BotAt(parse_cord(c)?)),
+            ["filled", c] => Ok(Breakpoint::Filled(parse_cord(c)?)),
+            ["energy", e] => Ok(Breakpoint::EnergyAbove(e.parse()?)),
+            _ => bail!("Invalid breakpoint: {}", s),
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Breakpoint::BotAt(c) => write!(f, "bot at {},{},{}", c.x, c.y, c.z),
+            Breakpoint::Filled(c) => write!(f, "filled {},{},{}", c.x, c.y, c.z),
+            Breakpoint::EnergyAbove(e) => write!(f, "energy above {}", e),
+        }
+    }
+}
+
+impl Breakpoint {
+    fn is_hit(&self, sim: &Simulator) -> bool {
+        match *self {
+            Breakpoint::BotAt(c) => sim.bots.iter().any(|b| b.pos == c),
+            Breakpoint::Filled(c) => c.is_in_range(sim.r) && sim.matrix[c],
+            Breakpoint::EnergyAbove(e) => sim.energy > e,
+        }
+    }
+}
+
+pub struct Debugger {
+    pub sim: Simulator,
+    cmds: Vec<Cmd>,
+    // A delta for each executed command.
+    deltas: Vec<Delta>,
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Debugger {
+    pub fn new(sim: Simulator, trace: Trace) -> Debugger {
+        Debugger {
+            sim,
+            cmds: trace.cmds,
+            deltas: vec![],
+            breakpoints: vec![],
+        }
+    }
+
+    /// The index of the next command.
+    pub fn position(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// Executes the next command. Returns false at the end of the trace.
+    pub fn forward(&mut self) -> Result<bool> {
+        let cmd = match self.cmds.get(self.position()) {
+            Some(cmd) => *cmd,
+            None => return Ok(false),
+        };
+        let delta = self
+            .sim
+            .step(cmd)
+            .map_err(|e| format_err!("command {}: {}: {}", self.position(), cmd, e))?;
+        self.deltas.push(delta);
+        Ok(true)
+    }
+
+    /// Undoes the last command. Returns false at the beginning of the trace.
+    pub fn backward(&mut self) -> bool {
+        match self.deltas.pop() {
+            Some(delta) => {
+                self.sim.undo(delta);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn forward_time_step(&mut self) -> Result<bool> {
+        if !self.forward()? {
+            return Ok(false);
+        }
+        while self.sim.bot_index != 0 && self.forward()? {}
+        Ok(true)
+    }
+
+    fn backward_time_step(&mut self) -> bool {
+        if !self.backward() {
+            return false;
+        }
+        while self.sim.bot_index != 0 && self.backward() {}
+        true
+    }
+
+    /// Goes to the beginning of time step `t`, or to the end of the trace.
+    pub fn goto(&mut self, t: usize) -> Result<()> {
+        while self.sim.time_step < t && self.forward()? {}
+        while (self.sim.time_step > t || self.sim.bot_index != 0) && self.backward() {}
+        Ok(())
+    }
+
+    // Goes until a breakpoint gets hit, and returns it.
+    fn run_to_breakpoint(&mut self, forward: bool) -> Result<Option<usize>> {
+        loop {
+            let before = self.hits();
+            let moved = if forward {
+                self.forward()?
+            } else {
+                self.backward()
+            };
+            if !moved {
+                return Ok(None);
+            }
+            let hit = self
+                .hits()
+                .into_iter()
+                .zip(before)
+                .position(|(now, before)| now && !before);
+            if hit.is_some() {
+                return Ok(hit);
+            }
+        }
+    }
+
+    fn hits(&self) -> Vec<bool> {
+        self.breakpoints
+            .iter()
+            .map(|b| b.is_hit(&self.sim))
+            .collect()
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn info(&self) -> String {
+        let next = match self.cmds.get(self.position()) {
+            Some(cmd) => match self.sim.bots.get(self.sim.bot_index) {
+                Some(bot) => format!("bot {}: {}", bot.bid, cmd),
+                None => cmd.to_string(),
+            },
+            None => "end of trace".to_string(),
+        };
+        format!(
+            "time step: {}, command: {}/{}, energy: {}, harmonics: {:?}, bots: {}\nnext: {}\n",
+            self.sim.time_step,
+            self.position(),
+            self.cmds.len(),
+            self.sim.energy,
+            self.sim.harmonics,
+            self.sim.bots.len(),
+            next
+        )
+    }
+
+    pub fn bots(&self) -> String {
+        // The commands of the current time step are before the position.
+        let start = self.position() - self.sim.bot_index;
+        self.sim
+            .bots
+            .iter()
+            .enumerate()
+            .map(|(i, bot)| {
+                let cmd = match self.cmds.get(start + i) {
+                    Some(cmd) if i < self.sim.bot_index => format!("done: {}", cmd),
+                    Some(cmd) => format!("next: {}", cmd),
+                    None => "-".to_string(),
+                };
+                format!(
+                    "bot {}: ({}, {}, {}), seeds: {}, {}\n",
+                    bot.bid,
+                    bot.pos.x,
+                    bot.pos.y,
+                    bot.pos.z,
+                    bot.seeds.len(),
+                    cmd
+                )
+            })
+            .collect()
+    }
+
+    /// Runs a command line of the debugger, and returns the output.
+    pub fn run(&mut self, line: &str) -> Result<String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return Ok(String::new()),
+        };
+        let count = || -> Result<usize> {
+            match args.first() {
+                Some(n) => Ok(n.parse()?),
+                None => Ok(1),
+            }
+        };
+        let mut message = String::new();
+        match command {
+            "s" | "step" => {
+                for _ in 0..count()? {
+                    self.forward()?;
+                }
+            }
+            "b" | "back" => {
+                for _ in 0..count()? {
+                    self.backward();
+                }
+            }
+            "n" | "next" => {
+                for _ in 0..count()? {
+                    self.forward_time_step()?;
+                }
+            }
+            "p" | "prev" => {
+                for _ in 0..count()? {
+                    self.backward_time_step();
+                }
+            }
+            "g" | "goto" => match args.first() {
+                Some(t) => self.goto(t.parse()?)?,
+                None => bail!("goto needs a time step"),
+            },
+            "c" | "continue" | "rc" | "reverse" => {
+                let forward = command == "c" || command == "continue";
+                if let Some(i) = self.run_to_breakpoint(forward)? {
+                    message = format!("breakpoint {}: {}\n", i, self.breakpoints[i]);
+                }
+            }
+            "break" => {
+                let breakpoint = args.join(" ").parse()?;
+                self.add_breakpoint(breakpoint);
+                return Ok(format!(
+                    "breakpoint {}: {}\n",
+                    self.breakpoints.len() - 1,
+                    breakpoint
+                ));
+            }
+            "breaks" => {
+                return Ok(self
+                    .breakpoints
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| format!("{}: {}\n", i, b))
+                    .collect());
+            }
+            "delete" => {
+                let i: usize = match args.first() {
+                    Some(i) => i.parse()?,
+                    None => bail!("delete needs a breakpoint"),
+                };
+                if i >= self.breakpoints.len() {
+                    bail!("No breakpoint: {}", i);
+                }
+                self.breakpoints.remove(i);
+                return Ok(String::new());
+            }
+            "i" | "info" => {}
+            "bots" => return Ok(self.bots()),
+            "render" => {
+                let axis = match args.first() {
+                    Some(axis) => axis.parse()?,
+                    None => Axis::Y,
+                };
+                let index = match args.get(1) {
+                    Some(index) => Some(index.parse::<i32>()?),
+                    None => None,
+                };
+                if let Some(index) = index {
+                    if index < 0 || index as usize >= self.sim.r {
+                        bail!("Index out of range: {}, resolution: {}", index, self.sim.r);
+                    }
+                }
+                let bots = self.sim.bots.iter().map(|b| b.pos).collect::<Vec<_>>();
+                let sim = &self.sim;
+                return Ok(format!(
+                    "{}\n{}",
+                    LEGEND,
+                    render_slices(&sim.matrix, axis, index, &bots, |c| sim.is_volatile(c))
+                ));
+            }
+            "h" | "help" => return Ok(format!("{}\n", HELP)),
+            _ => bail!("Unknown command: {}, try help", command),
+        }
+        Ok(message + &self.info())
+    }
+}
+
+/// Debugs the trace file interactively on the stdin.
+pub fn debug(src: Option<String>, tgt: Option<String>, trace: String) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let sim = simulator::start(problem.src.as_ref(), problem.tgt.as_ref())?;
+    let mut debugger = Debugger::new(sim, Trace::read(&trace)?);
+    print!("{}", debugger.info());
+
+    let stdin = std::io::stdin();
+    let mut last = String::new();
+    loop {
+        print!("(debug) ");
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = match line.trim() {
+            "" => last.clone(),
+            line => line.to_string(),
+        };
+        if line == "q" || line == "quit" {
+            break;
+        }
+        match debugger.run(&line) {
+            Ok(output) => print!("{}", output),
+            Err(e) => println!("error: {}", e),
+        }
+        last = line;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nanobot::model::*;
+
+    fn debugger() -> Debugger {
+        use self::Cmd::*;
+
+        let targets = vec![Cord::new(1, 0, 1), Cord::new(1, 1, 1)]
+            .into_iter()
+            .collect();
+        let tgt = Model::from_cords(ModelId::Assemble(None), 3, targets);
+        let trace = Trace {
+            cmds: vec![
+                SMove(LongLinear(CordDiff::new(0, 0, 1))),
+                Fission(Near(CordDiff::new(0, 1, 0)), 0),
+                Fill(Near(CordDiff::new(1, 0, 0))),
+                Fill(Near(CordDiff::new(1, 0, 0))),
+                FusionP(Near(CordDiff::new(0, 1, 0))),
+                FusionS(Near(CordDiff::new(0, -1, 0))),
+                SMove(LongLinear(CordDiff::new(0, 0, -1))),
+                Halt,
+            ],
+        };
+        Debugger::new(simulator::start(None, Some(&tgt)).unwrap(), trace)
+    }
+
+    #[test]
+    fn debugger_test() {
+        let mut d = debugger();
+        assert!(d
+            .run("n 3")
+            .unwrap()
+            .starts_with("time step: 3, command: 4/8"));
+        assert!(d.sim.matrix[Cord::new(1, 1, 1)]);
+        assert!(d
+            .bots()
+            .contains("bot 2: (0, 1, 1), seeds: 0, next: FusionS 0,-1,0"));
+
+        d.run("p").unwrap();
+        assert_eq!((d.sim.time_step, d.position()), (2, 2));
+        assert!(!d.sim.matrix[Cord::new(1, 0, 1)]);
+        d.run("b 2").unwrap();
+        assert_eq!((d.sim.time_step, d.position()), (0, 0));
+        assert_eq!(d.sim.energy, 0);
+
+        assert!(d
+            .run("break filled 1,1,1")
+            .unwrap()
+            .starts_with("breakpoint 0:"));
+        assert!(d
+            .run("c")
+            .unwrap()
+            .starts_with("breakpoint 0: filled 1,1,1\ntime step: 3,"));
+        d.run("delete 0").unwrap();
+        d.run("break energy 10000").unwrap();
+        assert!(d.run("c").unwrap().contains("end of trace"));
+        let energy = d.sim.energy;
+        d.run("rc").unwrap();
+        assert_eq!(d.position(), 0);
+        d.run("goto 100").unwrap();
+        assert_eq!((d.sim.energy, d.sim.halted), (energy, true));
+        d.run("goto 1").unwrap();
+        assert_eq!((d.sim.time_step, d.position()), (1, 1));
+
+        assert!(d.run("render y 0").unwrap().contains("y=0"));
+        assert!(d.run("break bot 1").is_err());
+        assert!(d.run("jump").is_err());
+    }
+}
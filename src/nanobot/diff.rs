@@ -0,0 +1,214 @@
+// Compares two traces of the same problem, time step by time step.
+//
+// A trace is split into three phases: deployment before the first Fill or
+// Void, building up to the last one, and gathering after it.
+
+use std::path::PathBuf;
+
+use super::bot::*;
+use super::model::*;
+use super::prelude::*;
+use super::problem::*;
+use super::simulator;
+use super::trace::*;
+
+pub const PHASES: [&str; 3] = ["deployment", "building", "gathering"];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Phase {
+    pub time_steps: usize,
+    pub energy: i64,
+}
+
+#[derive(Debug)]
+pub struct TraceProfile {
+    pub time_steps: usize,
+    pub cmds: usize,
+    pub energy: i64,
+    pub max_bots: usize,
+    pub phases: [Phase; 3],
+}
+
+impl TraceProfile {
+    /// Simulates `trace`. Fails if it is invalid, or does not end with `tgt`.
+    pub fn new(src: Option<&Model>, tgt: Option<&Model>, trace: &Trace) -> Result<TraceProfile> {
+        let mut sim = simulator::start(src, tgt)?;
+        let steps = trace.time_steps();
+        // The energy at the beginning of each time step, and at the end.
+        let mut energies = vec![0];
+        for step in &steps {
+            for cmd in step.iter() {
+                sim.execute(*cmd)?;
+            }
+            energies.push(sim.energy);
+        }
+        simulator::check_end(&sim, tgt)?;
+
+        let builds = |step: &&[Cmd]| {
+            step.iter()
+                .any(|cmd| matches!(cmd, Cmd::Fill(_) | Cmd::Void(_)))
+        };
+        let first = steps.iter().position(builds).unwrap_or(steps.len());
+        let last = steps.iter().rposition(builds).map_or(first, |i| i + 1);
+        let phase = |from: usize, to: usize| Phase {
+            time_steps: to - from,
+            energy: energies[to] - energies[from],
+        };
+        Ok(TraceProfile {
+            time_steps: steps.len(),
+            cmds: trace.cmds.len(),
+            energy: sim.energy,
+            max_bots: steps.iter().map(|step| step.len()).max().unwrap_or(0),
+            phases: [
+                phase(0, first),
+                phase(first, last),
+                phase(last, steps.len()),
+            ],
+        })
+    }
+
+    pub fn average_bots(&self) -> f64 {
+        self.cmds as f64 / self.time_steps.max(1) as f64
+    }
+}
+
+/// The first time step where the commands differ, if any.
+pub fn first_divergence(a: &Trace, b: &Trace) -> Option<usize> {
+    let (a, b) = (a.time_steps(), b.time_steps());
+    match a.iter().zip(b.iter()).position(|(a, b)| a != b) {
+        Some(i) => Some(i),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+fn format_step(trace: &Trace, t: usize) -> String {
+    match trace.time_steps().get(t) {
+        Some(cmds) => {
+            let mut text = cmds
+                .iter()
+                .take(8)
+                .map(|cmd| cmd.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            if cmds.len() > 8 {
+                text += &format!(" and {} more", cmds.len() - 8);
+            }
+            text
+        }
+        None => "-".to_string(),
+    }
+}
+
+pub fn diff_to_text(a: &Trace, b: &Trace, pa: &TraceProfile, pb: &TraceProfile) -> String {
+    let mut text = match first_divergence(a, b) {
+        Some(t) => format!(
+            "first divergent time step: {}\n  a: {}\n  b: {}\n",
+            t,
+            format_step(a, t),
+            format_step(b, t)
+        ),
+        None => "the traces are identical\n".to_string(),
+    };
+    let row = |name: &str, a: String, b: String, delta: String| {
+        format!("{:<24} {:>12} {:>12} {:>12}\n", name, a, b, delta)
+    };
+    let int_row = |name: &str, a: i64, b: i64| {
+        row(name, a.to_string(), b.to_string(), format!("{:+}", b - a))
+    };
+    text += &row("", "a".into(), "b".into(), "b - a".into());
+    text += &int_row("energy", pa.energy, pb.energy);
+    text += &int_row("time steps", pa.time_steps as i64, pb.time_steps as i64);
+    text += &int_row("commands", pa.cmds as i64, pb.cmds as i64);
+    text += &int_row("max bots", pa.max_bots as i64, pb.max_bots as i64);
+    text += &row(
+        "average bots",
+        format!("{:.2}", pa.average_bots()),
+        format!("{:.2}", pb.average_bots()),
+        format!("{:+.2}", pb.average_bots() - pa.average_bots()),
+    );
+    for (i, name) in PHASES.iter().enumerate() {
+        let (a, b) = (pa.phases[i], pb.phases[i]);
+        text += &int_row(&format!("{} energy", name), a.energy, b.energy);
+        text += &int_row(
+            &format!("{} time steps", name),
+            a.time_steps as i64,
+            b.time_steps as i64,
+        );
+    }
+    text
+}
+
+/// Prints the differences of the trace files `a` and `b` for the problem.
+pub fn print_diff(src: Option<String>, tgt: Option<String>, a: String, b: String) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let (src, tgt) = (problem.src.as_ref(), problem.tgt.as_ref());
+    let (ta, tb) = (Trace::read(&a)?, Trace::read(&b)?);
+    let pa = TraceProfile::new(src, tgt, &ta).map_err(|e| format_err!("{}: {}", a, e))?;
+    let pb = TraceProfile::new(src, tgt, &tb).map_err(|e| format_err!("{}: {}", b, e))?;
+    print!("{}", diff_to_text(&ta, &tb, &pa, &pb));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_test() {
+        use self::Cmd::*;
+
+        let targets = vec![Cord::new(1, 0, 1)].into_iter().collect();
+        let tgt = Model::from_cords(ModelId::Assemble(None), 3, targets);
+        let a = Trace {
+            cmds: vec![
+                SMove(LongLinear(CordDiff::new(0, 0, 1))),
+                Fill(Near(CordDiff::new(1, 0, 0))),
+                SMove(LongLinear(CordDiff::new(0, 0, -1))),
+                Halt,
+            ],
+        };
+        let b = Trace {
+            cmds: vec![
+                SMove(LongLinear(CordDiff::new(0, 0, 1))),
+                Wait,
+                Fill(Near(CordDiff::new(1, 0, 0))),
+                SMove(LongLinear(CordDiff::new(0, 0, -1))),
+                Halt,
+            ],
+        };
+        assert_eq!(first_divergence(&a, &b), Some(1));
+        assert_eq!(first_divergence(&a, &a), None);
+        let mut c = Trace {
+            cmds: a.cmds.clone(),
+        };
+        c.cmds.pop();
+        assert_eq!(first_divergence(&a, &c), Some(3));
+
+        let pa = TraceProfile::new(None, Some(&tgt), &a).unwrap();
+        let pb = TraceProfile::new(None, Some(&tgt), &b).unwrap();
+        // 81 for Low harmonics and 20 for a bot in each time step.
+        let phase = |time_steps, energy| Phase { time_steps, energy };
+        assert_eq!(pa.phases[0], phase(1, 101 + 2));
+        assert_eq!(pa.phases[1], phase(1, 101 + 12));
+        assert_eq!(pa.phases[2].time_steps, 2);
+        assert_eq!(pb.phases[0], phase(2, 202 + 2));
+        assert_eq!(pb.energy - pa.energy, 101);
+
+        let text = diff_to_text(&a, &b, &pa, &pb);
+        assert!(text.starts_with("first divergent time step: 1\n  a: Fill 1,0,0\n  b: Wait\n"));
+        assert!(text.contains(&format!(
+            "{:<24} {:>12} {:>12} {:>12}\n",
+            "time steps", 4, 5, "+1"
+        )));
+        let invalid = Trace {
+            cmds: vec![SMove(LongLinear(CordDiff::new(-1, 0, 0)))],
+        };
+        assert!(TraceProfile::new(None, Some(&tgt), &invalid).is_err());
+        // Without Halt.
+        assert!(TraceProfile::new(None, Some(&tgt), &c).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+// Isometric images of a matrix, written as PPM or PNG.
+//
+// A voxel at (x, y, z) is drawn as a cube whose corner (x, y, z) is projected
+// to (2t(x - z), t(x + z) - 2ty) on the screen, where t is the scale. The top,
+// +x and +z faces are visible and shaded differently. Cubes are drawn from
+// the back to the front, i.e. in the order of x + y + z.
+
+use flate2;
+use flate2::write::ZlibEncoder;
+
+use std;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::matrix::*;
+use super::prelude::*;
+use super::problem::*;
+use super::simulator;
+use super::trace::*;
+
+pub type Rgb = [u8; 3];
+
+const BACKGROUND: Rgb = [255, 255, 255];
+const FLOOR: Rgb = [232, 232, 232];
+const VOXEL: Rgb = [140, 170, 210];
+const BOT: Rgb = [230, 60, 50];
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, color: Rgb) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![color; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    pub fn encode_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for pixel in &self.pixels {
+            bytes.extend_from_slice(pixel);
+        }
+        bytes
+    }
+
+    pub fn encode_png(&self) -> Result<Vec<u8>> {
+        fn chunk(bytes: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(kind);
+            bytes.extend_from_slice(data);
+            let mut crc = flate2::Crc::new();
+            crc.update(kind);
+            crc.update(data);
+            bytes.extend_from_slice(&crc.sum().to_be_bytes());
+        }
+
+        let mut header = vec![];
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8 bit RGB, no interlace.
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut encoder = ZlibEncoder::new(vec![], flate2::Compression::default());
+        for row in self.pixels.chunks(self.width) {
+            // No filter.
+            encoder.write_all(&[0])?;
+            for pixel in row {
+                encoder.write_all(pixel)?;
+            }
+        }
+
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut bytes, b"IHDR", &header);
+        chunk(&mut bytes, b"IDAT", &encoder.finish()?);
+        chunk(&mut bytes, b"IEND", &[]);
+        Ok(bytes)
+    }
+
+    /// Writes PPM if the extension is .ppm, and PNG otherwise.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = match path.extension().and_then(|e| e.to_str()) {
+            Some("ppm") => self.encode_ppm(),
+            _ => self.encode_png()?,
+        };
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Face {
+    Top,
+    X,
+    Z,
+}
+
+// Where a cube covers in its 4t x 4t bounding box, relative to the projection
+// of its corner at (2t, 2t). Pixels on an edge of a face are marked.
+struct Sprite {
+    t: usize,
+    pixels: Vec<Option<(Face, bool)>>,
+}
+
+impl Sprite {
+    fn new(t: usize) -> Sprite {
+        let tf = t as f64;
+        let size = 4 * t;
+        let inside = |a: f64, b: f64| (0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b);
+        // Within half a pixel of an edge.
+        let edge = |a: f64, b: f64| a.min(1.0 - a).min(b).min(1.0 - b) * 2.0 * tf < 0.5;
+        let mut pixels = vec![None; size * size];
+        for j in 0..size {
+            for i in 0..size {
+                let dx = i as f64 + 0.5 - 2.0 * tf;
+                let dy = j as f64 + 0.5 - 2.0 * tf;
+                // Solve the corner + a * x + b * z (+ c * y) for each face.
+                let top = {
+                    let dy = dy + 2.0 * tf;
+                    (
+                        (dx / (2.0 * tf) + dy / tf) / 2.0,
+                        (dy / tf - dx / (2.0 * tf)) / 2.0,
+                    )
+                };
+                let x = {
+                    let b = (2.0 * tf - dx) / (2.0 * tf);
+                    (b, (tf * b - (dy - tf)) / (2.0 * tf))
+                };
+                let z = {
+                    let a = (dx + 2.0 * tf) / (2.0 * tf);
+                    (a, (tf * a - (dy - tf)) / (2.0 * tf))
+                };
+                pixels[j * size + i] = [(Face::Top, top), (Face::X, x), (Face::Z, z)]
+                    .iter()
+                    .find(|(_, (a, b))| inside(*a, *b))
+                    .map(|(face, (a, b))| (*face, edge(*a, *b)));
+            }
+        }
+        Sprite { t, pixels }
+    }
+}
+
+fn shade(color: Rgb, face: Face, edge: bool) -> Rgb {
+    let mut k = match face {
+        Face::Top => 1.0,
+        Face::X => 0.78,
+        Face::Z => 0.6,
+    };
+    if edge {
+        k *= 0.8;
+    }
+    [
+        (color[0] as f64 * k) as u8,
+        (color[1] as f64 * k) as u8,
+        (color[2] as f64 * k) as u8,
+    ]
+}
+
+/// A scale which makes an image of about 800 pixels wide.
+pub fn default_scale(r: usize) -> usize {
+    (200 / r.max(1)).max(1)
+}
+
+/// Renders `matrix` and `bots` with the scale `t`.
+pub fn render_isometric(matrix: &Matrix, bots: &[Cord], t: usize) -> Image {
+    let r = matrix.r as i64;
+    let ti = t as i64;
+    let margin = 2 * ti;
+    let size = (4 * ti * r + 2 * margin) as usize;
+    let mut image = Image::new(size, size, BACKGROUND);
+    let (ox, oy) = (2 * ti * r + margin, 2 * ti * r + margin);
+
+    // The floor, y = 0.
+    let tf = t as f64;
+    for py in 0..size {
+        for px in 0..size {
+            let dx = px as f64 + 0.5 - ox as f64;
+            let dy = py as f64 + 0.5 - oy as f64;
+            let a = (dx / (2.0 * tf) + dy / tf) / 2.0;
+            let b = (dy / tf - dx / (2.0 * tf)) / 2.0;
+            let r = r as f64;
+            if (0.0..=r).contains(&a) && (0.0..=r).contains(&b) {
+                image.set(px, py, FLOOR);
+            }
+        }
+    }
+
+    let mut cubes = vec![];
+    for x in 0..r as i32 {
+        for y in 0..r as i32 {
+            for z in 0..r as i32 {
+                let c = Cord::new(x, y, z);
+                if !matrix[c] {
+                    continue;
+                }
+                // Hidden behind its neighbors.
+                let hidden = [
+                    Cord::new(x + 1, y, z),
+                    Cord::new(x, y + 1, z),
+                    Cord::new(x, y, z + 1),
+                ]
+                .iter()
+                .all(|n| n.is_in_range(matrix.r) && matrix[*n] && !bots.contains(n));
+                if !hidden {
+                    cubes.push((c, VOXEL));
+                }
+            }
+        }
+    }
+    cubes.extend(bots.iter().map(|b| (*b, BOT)));
+    cubes.sort_by_key(|(c, _)| c.x + c.y + c.z);
+
+    let sprite = Sprite::new(t);
+    let sprite_size = 4 * sprite.t;
+    for (c, color) in cubes {
+        let (x, y, z) = (c.x as i64, c.y as i64, c.z as i64);
+        let sx = ox + 2 * ti * (x - z) - 2 * ti;
+        let sy = oy + ti * (x + z) - 2 * ti * y - 2 * ti;
+        for j in 0..sprite_size {
+            for i in 0..sprite_size {
+                if let Some((face, edge)) = sprite.pixels[j * sprite_size + i] {
+                    let px = (sx + i as i64) as usize;
+                    let py = (sy + j as i64) as usize;
+                    image.set(px, py, shade(color, face, edge));
+                }
+            }
+        }
+    }
+    image
+}
+
+// "frames/f.png" -> "frames/f-000012.png".
+fn frame_path(output: &Path, time_step: usize) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let filename = match output.extension() {
+        Some(ext) => format!("{}-{:06}.{}", stem, time_step, ext.to_string_lossy()),
+        None => format!("{}-{:06}", stem, time_step),
+    };
+    output.with_file_name(filename)
+}
+
+/// Renders a model to `output`. With `trace`, renders the final state, or a
+/// frame every `every` time steps and the last one, named like
+/// `<output stem>-000012.png`.
+pub fn write_image(
+    src: Option<String>,
+    tgt: Option<String>,
+    trace: Option<String>,
+    every: Option<usize>,
+    scale: Option<usize>,
+    output: &Path,
+) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let r = problem.r();
+    let t = scale.unwrap_or_else(|| default_scale(r));
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let trace = match trace {
+        Some(trace) => Trace::read(trace)?,
+        None => {
+            let model = problem.tgt.as_ref().or(problem.src.as_ref()).unwrap();
+            let matrix = Matrix::from_cords(r, &model.targets);
+            return render_isometric(&matrix, &[], t).write_to(output);
+        }
+    };
+    let mut sim = simulator::start(problem.src.as_ref(), problem.tgt.as_ref())?;
+    let steps = trace.time_steps();
+    let mut frames = 0;
+    for (i, cmds) in steps.iter().enumerate() {
+        if let Some(every) = every {
+            if i % every.max(1) == 0 {
+                let bots = sim.bots.iter().map(|b| b.pos).collect::<Vec<_>>();
+                render_isometric(&sim.matrix, &bots, t).write_to(frame_path(output, i))?;
+                frames += 1;
+            }
+        }
+        for cmd in cmds.iter() {
+            sim.execute(*cmd)?;
+        }
+    }
+    let bots = sim.bots.iter().map(|b| b.pos).collect::<Vec<_>>();
+    let image = render_isometric(&sim.matrix, &bots, t);
+    match every {
+        Some(_) => {
+            image.write_to(frame_path(output, steps.len()))?;
+            info!("Wrote {} frames", frames + 1);
+        }
+        None => image.write_to(output)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn encode_test() {
+        let mut image = Image::new(2, 1, [1, 2, 3]);
+        image.set(1, 0, [4, 5, 6]);
+        assert_eq!(
+            image.encode_ppm(),
+            b"P6\n2 1\n255\n\x01\x02\x03\x04\x05\x06"
+        );
+
+        let png = image.encode_png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..29], &[0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+        assert_eq!(&png[37..41], b"IDAT");
+        let len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        let mut raw = vec![];
+        ZlibDecoder::new(&png[41..41 + len])
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw, vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn render_isometric_test() {
+        let matrix = Matrix::from_cords(2, &[Cord::new(0, 0, 0)]);
+        let bots = vec![Cord::new(1, 0, 1)];
+        let t = 4;
+        let image = render_isometric(&matrix, &bots, t);
+        assert_eq!(image.width, 4 * 4 * 2 + 4 * 4);
+        // The middle of the top face of the voxel, and of the bot in front of
+        // it.
+        let (ox, oy) = (2 * t * 2 + 2 * t, 2 * t * 2 + 2 * t);
+        assert_eq!(image.get(ox, oy - 2 * t + t / 2 + 1), VOXEL);
+        assert_eq!(image.get(ox, oy - 2 * t + 2 * t + t + 1), BOT);
+        assert_eq!(image.get(0, 0), BACKGROUND);
+        assert!(image.pixels.contains(&FLOOR));
+        assert!(image.pixels.contains(&shade(VOXEL, Face::X, false)));
+        assert!(image.pixels.contains(&shade(VOXEL, Face::Z, false)));
+    }
+
+    #[test]
+    fn frame_path_test() {
+        assert_eq!(
+            frame_path(Path::new("frames/f.png"), 12),
+            PathBuf::from("frames/f-000012.png")
+        );
+        assert_eq!(frame_path(Path::new("f"), 0), PathBuf::from("f-000000"));
+    }
+}
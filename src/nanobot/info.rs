@@ -0,0 +1,172 @@
+// Structural statistics of a model, to see what a strategy is up against.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::model::*;
+use super::prelude::*;
+
+pub struct ModelInfo {
+    pub r: usize,
+    pub voxels: usize,
+    /// The minimum and maximum corners, if the model has any voxel.
+    pub bounding_box: Option<(Cord, Cord)>,
+    /// The number of voxels in each y layer, from the bottom.
+    pub layers: Vec<usize>,
+    /// Connected by faces.
+    pub components: usize,
+    /// Voxels which are not connected to the floor.
+    pub ungrounded: usize,
+    /// Voxels above the floor which have no voxel right below.
+    pub overhangs: usize,
+    /// Voxels outside of the contest bounds, 1 <= x, z <= r - 2 and
+    /// y <= r - 2.
+    pub out_of_bounds: usize,
+}
+
+// Voxels of `targets` which are connected by faces to `start`.
+fn flood(targets: &HashSet<Cord>, start: Vec<Cord>, visited: &mut HashSet<Cord>) {
+    let mut stack = start;
+    while let Some(c) = stack.pop() {
+        for diff in CordDiff::gen_all_diff() {
+            let n = c + *diff;
+            if targets.contains(&n) && visited.insert(n) {
+                stack.push(n);
+            }
+        }
+    }
+}
+
+impl ModelInfo {
+    pub fn new(model: &Model) -> ModelInfo {
+        let targets = &model.targets;
+        let r = model.r as i32;
+
+        let bounding_box = targets.iter().fold(None, |acc, c| match acc {
+            None => Some((*c, *c)),
+            Some((min, max)) => Some((
+                Cord::new(min.x.min(c.x), min.y.min(c.y), min.z.min(c.z)),
+                Cord::new(max.x.max(c.x), max.y.max(c.y), max.z.max(c.z)),
+            )),
+        });
+        let mut layers = vec![0; bounding_box.map_or(0, |(_, max)| max.y as usize + 1)];
+        for c in targets {
+            layers[c.y as usize] += 1;
+        }
+
+        let mut visited = HashSet::new();
+        let mut components = 0;
+        for c in targets {
+            if visited.insert(*c) {
+                flood(targets, vec![*c], &mut visited);
+                components += 1;
+            }
+        }
+
+        let floor = targets
+            .iter()
+            .filter(|c| c.y == 0)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut grounded = floor.iter().cloned().collect::<HashSet<_>>();
+        flood(targets, floor, &mut grounded);
+
+        ModelInfo {
+            r: model.r,
+            voxels: targets.len(),
+            bounding_box,
+            layers,
+            components,
+            ungrounded: targets.len() - grounded.len(),
+            overhangs: targets
+                .iter()
+                .filter(|c| c.y > 0 && !targets.contains(&Cord::new(c.x, c.y - 1, c.z)))
+                .count(),
+            out_of_bounds: targets
+                .iter()
+                .filter(|c| c.x < 1 || c.x > r - 2 || c.z < 1 || c.z > r - 2 || c.y > r - 2)
+                .count(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        let mut text = format!("resolution: {}\nvoxels: {}\n", self.r, self.voxels);
+        text += &match self.bounding_box {
+            Some((min, max)) => format!(
+                "bounding box: ({}, {}, {}) - ({}, {}, {}), size: {}x{}x{}\n",
+                min.x,
+                min.y,
+                min.z,
+                max.x,
+                max.y,
+                max.z,
+                max.x - min.x + 1,
+                max.y - min.y + 1,
+                max.z - min.z + 1
+            ),
+            None => "bounding box: -\n".to_string(),
+        };
+        text += &format!(
+            "components: {}\ngrounded: {} (ungrounded voxels: {})\noverhangs: {}\n",
+            self.components,
+            yes_no(self.ungrounded == 0),
+            self.ungrounded,
+            self.overhangs
+        );
+        text += &format!(
+            "in contest bounds: {} (voxels out of bounds: {})\nlayers:\n",
+            yes_no(self.out_of_bounds == 0),
+            self.out_of_bounds
+        );
+        for (y, count) in self.layers.iter().enumerate() {
+            text += &format!("  y={}: {}\n", y, count);
+        }
+        text
+    }
+}
+
+pub fn print_info(path: &Path) -> Result<()> {
+    let model = Model::read(ModelId::Assemble(None), path)?;
+    print!("{}", ModelInfo::new(&model).to_text());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn model_info_test() {
+        // An L-shaped pillar, an overhang, and a floating voxel on the edge.
+        let targets = vec![
+            Cord::new(1, 0, 1),
+            Cord::new(1, 1, 1),
+            Cord::new(2, 1, 1),
+            Cord::new(3, 3, 0),
+        ]
+        .into_iter()
+        .collect();
+        let model = Model::from_cords(ModelId::Assemble(None), 5, targets);
+        let info = ModelInfo::new(&model);
+        assert_eq!(info.voxels, 4);
+        assert_eq!(
+            info.bounding_box,
+            Some((Cord::new(1, 0, 0), Cord::new(3, 3, 1)))
+        );
+        assert_eq!(info.layers, vec![1, 2, 0, 1]);
+        assert_eq!(info.components, 2);
+        assert_eq!(info.ungrounded, 1);
+        assert_eq!(info.overhangs, 2);
+        assert_eq!(info.out_of_bounds, 1);
+        assert!(info
+            .to_text()
+            .contains("grounded: no (ungrounded voxels: 1)"));
+
+        let empty = Model::from_cords(ModelId::Assemble(None), 5, HashSet::new());
+        let info = ModelInfo::new(&empty);
+        assert_eq!(info.bounding_box, None);
+        assert_eq!(info.components, 0);
+        assert!(info.layers.is_empty());
+    }
+}
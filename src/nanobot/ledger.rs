@@ -0,0 +1,242 @@
+// The submission ledger, `submit.json` in the submit directory.
+//
+// It has the best trace of every problem, and every improvement accepted
+// before it. A trace is accepted only if the simulator reproduces its energy.
+
+use chrono::Local;
+use serde_json;
+use sha2::{Digest, Sha256};
+
+use std;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::ai::Strategy;
+use super::contest;
+use super::prelude::*;
+use super::problem::*;
+use super::run::RunResult;
+use super::simulator::{self, SimulateResult};
+
+/// `git describe` of the build.
+pub const REVISION: &str = env!("GIT_REVISION");
+
+#[derive(Fail, Debug)]
+#[fail(
+    display = "Rejected trace: model: {}, energy: {}, simulated: {}",
+    model_name, energy, simulated
+)]
+pub struct RejectedTrace {
+    pub model_name: String,
+    pub energy: i64,
+    pub simulated: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    pub ai: String,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub energy: i64,
+    // Entries written by older versions have only the fields above.
+    #[serde(default)]
+    pub revision: Option<String>,
+    #[serde(default)]
+    pub time_steps: Option<usize>,
+    #[serde(default)]
+    pub solve_millis: Option<u64>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LedgerRecord {
+    #[serde(flatten)]
+    pub best: LedgerEntry,
+    /// Entries superseded by `best`, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<LedgerEntry>,
+}
+
+pub struct Ledger {
+    dir: PathBuf,
+    pub records: BTreeMap<String, LedgerRecord>,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+impl LedgerEntry {
+    /// Whether this entry was produced by `strategy`.
+    pub fn is_from(&self, strategy: Strategy) -> bool {
+        self.ai == format!("{:?}", strategy.ai) && self.seed == strategy.seed
+    }
+}
+
+impl Ledger {
+    pub fn read() -> Result<Ledger> {
+        Ledger::read_from(contest::submit_dir())
+    }
+
+    pub fn read_from(dir: impl AsRef<Path>) -> Result<Ledger> {
+        let dir = dir.as_ref().to_path_buf();
+        let path = dir.join("submit.json");
+        let records = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            Default::default()
+        };
+        Ok(Ledger { dir, records })
+    }
+
+    pub fn write(&self) -> Result<()> {
+        debug!("Writing ledger");
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(
+            self.dir.join("submit.json"),
+            serde_json::to_string_pretty(&self.records)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn best(&self, model_name: &str) -> Option<&LedgerEntry> {
+        self.records.get(model_name).map(|r| &r.best)
+    }
+
+    pub fn trace_path(&self, model_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.nbt", model_name))
+    }
+
+    fn is_best(&self, model_name: &str, energy: i64) -> bool {
+        match self.best(model_name) {
+            Some(best) => energy < best.energy,
+            None => true,
+        }
+    }
+
+    /// Simulates the trace of `run_result` for `Ledger::accept`. An error is
+    /// returned if the simulator does not reproduce its energy.
+    pub fn verify(problem: &Problem, run_result: &RunResult) -> Result<SimulateResult> {
+        let energy = run_result.energy.ok_or_err()?;
+        let result = simulator::verify(problem, &run_result.trace)?;
+        if result.energy != energy {
+            return Err(RejectedTrace {
+                model_name: problem.name.clone(),
+                energy,
+                simulated: result.energy,
+            }
+            .into());
+        }
+        Ok(result)
+    }
+
+    /// Records `run_result` and writes its trace if it beats the best one.
+    /// Returns whether it is accepted. `result` is the simulation of its trace
+    /// by `Ledger::verify`, which needs no lock on the ledger.
+    pub fn accept(
+        &mut self,
+        problem: &Problem,
+        run_result: &RunResult,
+        result: &SimulateResult,
+    ) -> Result<bool> {
+        let energy = result.energy;
+        if !self.is_best(&problem.name, energy) {
+            return Ok(false);
+        }
+        info!(
+            "Found the best score: model: {}, strategy: {}, energy: {}",
+            problem.name, run_result.strategy, energy
+        );
+        let bytes = run_result.trace.encode();
+        let entry = LedgerEntry {
+            ai: format!("{:?}", run_result.strategy.ai),
+            seed: run_result.strategy.seed,
+            energy,
+            revision: Some(REVISION.to_string()),
+            time_steps: Some(result.time_steps),
+            solve_millis: Some(run_result.elapsed.as_millis() as u64),
+            sha256: Some(sha256_hex(&bytes)),
+            date: Some(Local::now().to_rfc3339()),
+        };
+        match self.records.remove(&problem.name) {
+            Some(mut record) => {
+                record.history.push(record.best);
+                record.best = entry;
+                self.records.insert(problem.name.clone(), record);
+            }
+            None => {
+                self.records.insert(
+                    problem.name.clone(),
+                    LedgerRecord {
+                        best: entry,
+                        history: vec![],
+                    },
+                );
+            }
+        }
+        let path = self.trace_path(&problem.name);
+        info!("Writing best trace to: {}", path.display());
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(path, bytes)?;
+        self.write()?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nanobot::ai::*;
+    use nanobot::deadline::*;
+    use nanobot::model::*;
+    use nanobot::run::solve;
+
+    #[test]
+    fn ledger_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-ledger-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("submit.json"),
+            r#"{"FA000": {"ai": "Many(2)", "energy": 1000000000}}"#,
+        )
+        .unwrap();
+
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let problem = Problem::new(
+            "FA000".to_string(),
+            None,
+            Some(Model::from_cords(ModelId::Assemble(None), 6, targets)),
+        );
+        let mut run_result = solve(&problem, Ai::Many(2).into(), &Deadline::none()).unwrap();
+
+        let mut ledger = Ledger::read_from(&dir).unwrap();
+        assert_eq!(ledger.best("FA000").unwrap().revision, None);
+        let result = Ledger::verify(&problem, &run_result).unwrap();
+        assert!(ledger.accept(&problem, &run_result, &result).unwrap());
+        assert!(!ledger.accept(&problem, &run_result, &result).unwrap());
+
+        let ledger = Ledger::read_from(&dir).unwrap();
+        let record = ledger.records["FA000"].clone();
+        assert_eq!(record.best.energy, run_result.energy.unwrap());
+        assert_eq!(record.best.revision.as_ref().unwrap(), REVISION);
+        assert_eq!(
+            record.best.sha256,
+            Some(sha256_hex(
+                &std::fs::read(ledger.trace_path("FA000")).unwrap()
+            ))
+        );
+        assert_eq!(record.history.len(), 1);
+        assert_eq!(record.history[0].energy, 1000000000);
+
+        // A trace whose energy is not reproduced is rejected.
+        run_result.energy = Some(run_result.energy.unwrap() - 1);
+        assert!(Ledger::verify(&problem, &run_result).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
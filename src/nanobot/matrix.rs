@@ -12,20 +12,26 @@ impl Matrix {
     pub fn new(model: &Model) -> Matrix {
         let r = model.r;
         match model.id {
-            ModelId::Assemble(_) => Matrix {
-                r,
-                full: vec![false; r * r * r],
-            },
-            ModelId::Disassemble(_) => {
-                let mut full = vec![false; r * r * r];
-                for c in &model.targets {
-                    full[c.to_linear_index(r)] = true;
-                }
-                Matrix { r, full }
-            }
+            ModelId::Assemble(_) => Matrix::empty(r),
+            ModelId::Disassemble(_) => Matrix::from_cords(r, &model.targets),
         }
     }
 
+    pub fn empty(r: usize) -> Matrix {
+        Matrix {
+            r,
+            full: vec![false; r * r * r],
+        }
+    }
+
+    pub fn from_cords<'a>(r: usize, cords: impl IntoIterator<Item = &'a Cord>) -> Matrix {
+        let mut matrix = Matrix::empty(r);
+        for c in cords {
+            matrix.full[c.to_linear_index(r)] = true;
+        }
+        matrix
+    }
+
     pub fn fill(&mut self, c: Cord) {
         // 1. void -> full
         debug_assert!(!self.full[c.to_linear_index(self.r)]);
@@ -0,0 +1,268 @@
+// Surface meshes of a matrix, written as OBJ or STL.
+//
+// Only faces between a full voxel and a void (or the outside of the matrix)
+// are exposed. In each plane, adjacent faces which look the same way are
+// merged greedily into rectangles: first along u, then along v. Vertices are
+// in voxel units, and quads are counter-clockwise seen from the outside.
+
+use std;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::matrix::*;
+use super::prelude::*;
+use super::problem::*;
+use super::simulator;
+use super::trace::*;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Quad {
+    pub corners: [Cord; 4],
+    /// 0, 1 or 2 for x, y or z.
+    pub axis: usize,
+    pub positive: bool,
+}
+
+impl Quad {
+    pub fn normal(&self) -> Cord {
+        let mut n = [0; 3];
+        n[self.axis] = if self.positive { 1 } else { -1 };
+        to_cord(n)
+    }
+
+    pub fn area(&self) -> u64 {
+        (self.corners[1] - self.corners[0]).mlen() * (self.corners[3] - self.corners[0]).mlen()
+    }
+}
+
+fn to_cord(a: [i32; 3]) -> Cord {
+    Cord::new(a[0], a[1], a[2])
+}
+
+fn is_full(matrix: &Matrix, a: [i32; 3]) -> bool {
+    let r = matrix.r as i32;
+    a.iter().all(|&i| 0 <= i && i < r) && matrix[to_cord(a)]
+}
+
+pub struct Mesh {
+    pub quads: Vec<Quad>,
+}
+
+impl Mesh {
+    pub fn new(matrix: &Matrix) -> Mesh {
+        let r = matrix.r as i32;
+        let mut quads = vec![];
+        for axis in 0..3 {
+            // (axis, u, v) is right-handed, so u x v is the positive normal.
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            for s in 0..=r {
+                // The faces on the plane at s, by (u, v).
+                let mut mask = vec![None; (r * r) as usize];
+                for j in 0..r {
+                    for i in 0..r {
+                        let mut a = [0; 3];
+                        a[u] = i;
+                        a[v] = j;
+                        a[axis] = s - 1;
+                        let below = is_full(matrix, a);
+                        a[axis] = s;
+                        let above = is_full(matrix, a);
+                        if below != above {
+                            mask[(j * r + i) as usize] = Some(below);
+                        }
+                    }
+                }
+                for j in 0..r {
+                    let mut i = 0;
+                    while i < r {
+                        let positive = match mask[(j * r + i) as usize] {
+                            Some(positive) => positive,
+                            None => {
+                                i += 1;
+                                continue;
+                            }
+                        };
+                        let face = Some(positive);
+                        let mut w = 1;
+                        while i + w < r && mask[(j * r + i + w) as usize] == face {
+                            w += 1;
+                        }
+                        let mut h = 1;
+                        while j + h < r
+                            && (i..i + w).all(|k| mask[((j + h) * r + k) as usize] == face)
+                        {
+                            h += 1;
+                        }
+                        for jj in j..j + h {
+                            for k in i..i + w {
+                                mask[(jj * r + k) as usize] = None;
+                            }
+                        }
+                        let corner = |du: i32, dv: i32| {
+                            let mut a = [0; 3];
+                            a[axis] = s;
+                            a[u] = i + du;
+                            a[v] = j + dv;
+                            to_cord(a)
+                        };
+                        let mut corners = [corner(0, 0), corner(w, 0), corner(w, h), corner(0, h)];
+                        if !positive {
+                            corners.reverse();
+                        }
+                        quads.push(Quad {
+                            corners,
+                            axis,
+                            positive,
+                        });
+                        i += w;
+                    }
+                }
+            }
+        }
+        Mesh { quads }
+    }
+
+    pub fn to_obj(&self) -> String {
+        let mut vertices = HashMap::new();
+        let mut text = String::new();
+        let mut faces = String::new();
+        for quad in &self.quads {
+            let mut indices = vec![];
+            for c in &quad.corners {
+                let n = vertices.len() + 1;
+                let index = *vertices.entry(*c).or_insert_with(|| {
+                    text += &format!("v {} {} {}\n", c.x, c.y, c.z);
+                    n
+                });
+                indices.push(index);
+            }
+            let normal = quad.axis * 2 + if quad.positive { 1 } else { 2 };
+            faces += "f";
+            for index in indices {
+                faces += &format!(" {}//{}", index, normal);
+            }
+            faces.push('\n');
+        }
+        for axis in 0..3 {
+            for sign in &[1, -1] {
+                let mut n = [0; 3];
+                n[axis] = *sign;
+                text += &format!("vn {} {} {}\n", n[0], n[1], n[2]);
+            }
+        }
+        text + &faces
+    }
+
+    /// ASCII STL, two triangles per quad.
+    pub fn to_stl(&self, name: &str) -> String {
+        let mut text = format!("solid {}\n", name);
+        for quad in &self.quads {
+            let n = quad.normal();
+            for triangle in &[[0, 1, 2], [0, 2, 3]] {
+                text += &format!("  facet normal {} {} {}\n    outer loop\n", n.x, n.y, n.z);
+                for i in triangle {
+                    let c = quad.corners[*i];
+                    text += &format!("      vertex {} {} {}\n", c.x, c.y, c.z);
+                }
+                text += "    endloop\n  endfacet\n";
+            }
+        }
+        text + &format!("endsolid {}\n", name)
+    }
+
+    /// .obj for OBJ, and .stl for STL.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("obj") => self.to_obj(),
+            Some("stl") => {
+                let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("mesh");
+                self.to_stl(name)
+            }
+            _ => bail!("Unknown mesh format: {}", path.display()),
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Writes the mesh of a model to `output`, or of the final matrix of `trace`
+/// if it is given.
+pub fn write_mesh(
+    src: Option<String>,
+    tgt: Option<String>,
+    trace: Option<String>,
+    output: &Path,
+) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let r = problem.r();
+    let matrix = match trace {
+        Some(trace) => {
+            let trace = Trace::read(trace)?;
+            let mut sim = simulator::start(problem.src.as_ref(), problem.tgt.as_ref())?;
+            for cmd in &trace.cmds {
+                sim.execute(*cmd)?;
+            }
+            sim.matrix
+        }
+        None => {
+            let model = problem.tgt.as_ref().or(problem.src.as_ref()).unwrap();
+            Matrix::from_cords(r, &model.targets)
+        }
+    };
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mesh = Mesh::new(&matrix);
+    mesh.write_to(output)?;
+    info!("Wrote {} quads", mesh.quads.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mesh_test() {
+        let cube = Mesh::new(&Matrix::from_cords(3, &[Cord::new(1, 1, 1)]));
+        assert_eq!(cube.quads.len(), 6);
+        for quad in &cube.quads {
+            assert_eq!(quad.area(), 1);
+            // Counter-clockwise seen from the outside.
+            let a = quad.corners[1] - quad.corners[0];
+            let b = quad.corners[2] - quad.corners[1];
+            let cross = Cord::new(
+                a.dy * b.dz - a.dz * b.dy,
+                a.dz * b.dx - a.dx * b.dz,
+                a.dx * b.dy - a.dy * b.dx,
+            );
+            assert_eq!(cross, quad.normal());
+        }
+
+        // A 3x1x2 box is merged into 6 faces, and touches the matrix bounds.
+        let cords = (0..3)
+            .flat_map(|x| (0..2).map(move |z| Cord::new(x, 0, z)))
+            .collect::<Vec<_>>();
+        let mesh = Mesh::new(&Matrix::from_cords(3, &cords));
+        assert_eq!(mesh.quads.len(), 6);
+        assert_eq!(
+            mesh.quads.iter().map(|q| q.area()).sum::<u64>(),
+            2 * (3 + 6 + 2)
+        );
+
+        let obj = mesh.to_obj();
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.matches("vn ").count(), 6);
+        assert_eq!(obj.matches("f ").count(), 6);
+        let stl = mesh.to_stl("box");
+        assert!(stl.starts_with("solid box\n"));
+        assert!(stl.ends_with("endsolid box\n"));
+        assert_eq!(stl.matches("facet normal").count(), 12);
+
+        assert!(Mesh::new(&Matrix::empty(3)).quads.is_empty());
+    }
+}
@@ -1,14 +1,57 @@
 mod ai;
 mod bot;
+mod bound;
+mod cache;
+mod cluster;
+mod contest;
+mod deadline;
+mod debug;
+mod diff;
+mod image;
+mod info;
+mod ledger;
 mod matrix;
+mod mesh;
 mod model;
+mod optimize;
+mod package;
+mod playback;
 mod prelude;
+mod problem;
+mod progress;
+mod render;
+mod report;
+mod rng;
 mod run;
+mod score;
+mod simulator;
+mod stats;
 mod system;
 mod target;
 mod trace;
+mod traffic;
 
-pub use self::ai::Ai;
+pub use self::ai::{Ai, Strategy};
+pub use self::cluster::work;
+pub use self::contest::{default_telemetry_path, set_contest_dir, set_problems_dir};
+pub use self::deadline::Deadline;
+pub use self::debug::{debug, Breakpoint, Debugger};
+pub use self::diff::{print_diff, TraceProfile};
+pub use self::image::{write_image, Image};
+pub use self::info::{print_info, ModelInfo};
+pub use self::ledger::{Ledger, LedgerEntry, LedgerRecord};
+pub use self::mesh::{write_mesh, Mesh};
+pub use self::model::{Model, ModelId};
+pub use self::optimize::{optimize, write_optimized, Optimized};
+pub use self::package::package;
+pub use self::playback::{write_playback, Frame};
 pub use self::prelude::Result;
-pub use self::run::ci;
-pub use self::run::run;
+pub use self::problem::{discover_problems, Problem, ProblemFiles, ProblemKind};
+pub use self::render::{print_render, Axis};
+pub use self::report::{CiReport, ModelReport, Status};
+pub use self::run::{ci, run, solve_portfolio, CiConfig, Portfolio, RunResult};
+pub use self::score::{print_scores, ScoreTable};
+pub use self::simulator::{print_simulation, SimulateReport};
+pub use self::stats::{print_stats, TraceStats};
+pub use self::trace::{asm, disasm, Trace};
+pub use self::traffic::{print_traffic, Traffic};
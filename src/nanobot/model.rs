@@ -1,9 +1,8 @@
 use std;
 use std::collections::HashSet;
-use std::io::prelude::*;
 use std::path::Path;
-use std::path::PathBuf;
 
+use super::contest;
 use super::prelude::*;
 
 pub struct Model {
@@ -13,19 +12,22 @@ pub struct Model {
     pub targets: HashSet<Cord>,
 }
 
-#[derive(Copy, Clone, Debug)]
+// The name of the problem which the model belongs to, e.g. "FA001" or
+// "FR001". The source model of a reassembly problem is `Disassemble` and the
+// target one is `Assemble`.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ModelId {
-    Assemble(Option<usize>),
-    Disassemble(Option<usize>),
+    Assemble(Option<String>),
+    Disassemble(Option<String>),
 }
 
 impl ModelId {
     pub fn name(&self) -> String {
         use self::ModelId::*;
         match self {
-            Assemble(Some(id)) => format!("FA{:03}", id),
+            Assemble(Some(name)) => name.clone(),
             Assemble(None) => "Assemple(None)".to_string(),
-            Disassemble(Some(id)) => format!("FD{:03}", id),
+            Disassemble(Some(name)) => name.clone(),
             Disassemble(None) => "Disassemple(Unknown)".to_string(),
         }
     }
@@ -33,9 +35,9 @@ impl ModelId {
     pub fn filename(&self) -> String {
         use self::ModelId::*;
         match self {
-            Assemble(Some(id)) => format!("FA{:03}_tgt.mdl", id),
+            Assemble(Some(name)) => format!("{}_tgt.mdl", name),
             Assemble(None) => unreachable!(),
-            Disassemble(Some(id)) => format!("FD{:03}_src.mdl", id),
+            Disassemble(Some(name)) => format!("{}_src.mdl", name),
             Disassemble(None) => unreachable!(),
         }
     }
@@ -43,24 +45,31 @@ impl ModelId {
 
 impl Model {
     pub fn read_contest_model(id: ModelId) -> Result<Model> {
-        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        path.push(format!("contest/model/{}", id.filename()));
+        let path = contest::problems_dir().join(id.filename());
         Model::read(id, path)
     }
 
     pub fn read(id: ModelId, path: impl AsRef<Path>) -> Result<Model> {
         let path = path.as_ref();
         debug!("read: {}", path.display());
-        let mut f = std::fs::File::open(path)?;
-
-        let mut head = [0; 1];
-        f.read_exact(&mut head)?;
-        let r = head[0] as usize;
+        let bytes = std::fs::read(path)?;
+        debug!("bytes: size: {}", bytes.len());
+        Model::decode(id, &bytes)
+    }
 
-        let mut bytes = vec![];
-        let size = f.read_to_end(&mut bytes)?;
-        debug!("bytes: size: {}", size);
+    /// Decodes the bytes of a .mdl file.
+    pub fn decode(id: ModelId, bytes: &[u8]) -> Result<Model> {
+        if bytes.is_empty() {
+            bail!("Empty model: {}", id.name());
+        }
+        let r = bytes[0] as usize;
+        if bytes.len() < 1 + (r * r * r).div_ceil(8) {
+            bail!("Truncated model: {}", id.name());
+        }
+        Ok(Model::from_bytes(id, r, bytes[1..].to_vec()))
+    }
 
+    pub fn from_bytes(id: ModelId, r: usize, bytes: Vec<u8>) -> Model {
         let mut targets = HashSet::new();
         for x in 0..r {
             for y in 0..r {
@@ -75,12 +84,33 @@ impl Model {
             }
         }
 
-        Ok(Model {
+        Model {
             id,
             r,
             bytes,
             targets,
-        })
+        }
+    }
+
+    pub fn from_cords(id: ModelId, r: usize, targets: HashSet<Cord>) -> Model {
+        let mut bytes = vec![0; (r * r * r).div_ceil(8)];
+        for c in &targets {
+            let index = c.to_linear_index(r);
+            bytes[index / 8] |= 1 << (index % 8);
+        }
+        Model {
+            id,
+            r,
+            bytes,
+            targets,
+        }
+    }
+
+    /// The content of a .mdl file.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![self.r as u8];
+        bytes.extend_from_slice(&self.bytes);
+        bytes
     }
 }
 
@@ -90,7 +120,8 @@ mod test {
 
     #[test]
     fn model_test() {
-        let model = Model::read_contest_model(ModelId::Assemble(Some(1))).unwrap();
+        let model =
+            Model::read_contest_model(ModelId::Assemble(Some("FA001".to_string()))).unwrap();
         assert_eq!(model.r, 20);
         assert_eq!(model.bytes.len(), 20 * 20 * 20 / 8);
         assert_eq!(
@@ -104,4 +135,28 @@ mod test {
         assert_eq!(model.targets.len(), 511);
     }
 
+    #[test]
+    fn decode_test() {
+        let targets = vec![Cord::new(1, 0, 1)].into_iter().collect();
+        let bytes = Model::from_cords(ModelId::Assemble(None), 3, targets).encode();
+        let model = Model::decode(ModelId::Assemble(None), &bytes).unwrap();
+        assert_eq!(model.targets.len(), 1);
+        assert!(Model::decode(ModelId::Assemble(None), &bytes[..bytes.len() - 1]).is_err());
+        assert!(Model::decode(ModelId::Assemble(None), &[3]).is_err());
+        assert!(Model::decode(ModelId::Assemble(None), &[]).is_err());
+    }
+
+    #[test]
+    fn read_truncated_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-model-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let targets = vec![Cord::new(1, 0, 1)].into_iter().collect();
+        let bytes = Model::from_cords(ModelId::Assemble(None), 20, targets).encode();
+        let path = dir.join("FA001_tgt.mdl");
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(Model::read(ModelId::Assemble(None), &path).unwrap().r, 20);
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(Model::read(ModelId::Assemble(None), &path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
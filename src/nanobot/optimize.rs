@@ -0,0 +1,373 @@
+// A peephole optimizer of traces. Each rewrite keeps what a trace builds, and
+// its result is kept only if the simulator accepts it with less energy:
+//
+// - High harmonics is turned on as late and off as early as the grounded rule
+//   allows. A Flip moves only into the slot of a Wait.
+// - Fills of a single bot from one position in consecutive time steps are
+//   done from the bottom up, and then High harmonics is shrunk again. Fills
+//   from different positions, or of several bots, are not reordered.
+// - Time steps where every bot waits are dropped.
+// - Moves of a single bot in consecutive time steps are merged. Legs on one
+//   axis are merged into an SMove unless they cancel out, and legs on two
+//   axes into an LMove, so that no merged move passes a voxel twice.
+
+use std::path::{Path, PathBuf};
+
+use super::bot::*;
+use super::prelude::*;
+use super::problem::*;
+use super::simulator::{self, InvalidTrace, SimulateResult};
+use super::trace::*;
+
+type Steps = Vec<Vec<Cmd>>;
+type Rewrite = fn(&Problem, &[Vec<Cmd>]) -> Steps;
+
+fn to_steps(trace: &Trace) -> Steps {
+    trace.time_steps().into_iter().map(|s| s.to_vec()).collect()
+}
+
+fn to_trace(steps: &[Vec<Cmd>]) -> Trace {
+    Trace {
+        cmds: steps.iter().flat_map(|s| s.iter().cloned()).collect(),
+    }
+}
+
+fn wait_slot(step: &[Cmd]) -> Option<usize> {
+    step.iter().position(|cmd| *cmd == Cmd::Wait)
+}
+
+// The time step where `steps` gets invalid, or None if it is valid.
+fn invalid_at(problem: &Problem, steps: &[Vec<Cmd>]) -> Option<usize> {
+    match simulator::verify(problem, &to_trace(steps)) {
+        Ok(_) => None,
+        Err(e) => Some(match e.downcast_ref::<InvalidTrace>() {
+            Some(e) => e.time_step,
+            None => 0,
+        }),
+    }
+}
+
+fn shrink_high(problem: &Problem, steps: &[Vec<Cmd>]) -> Steps {
+    let mut steps = steps.to_vec();
+    let flips = steps
+        .iter()
+        .enumerate()
+        .flat_map(|(t, step)| {
+            step.iter()
+                .enumerate()
+                .filter(|(_, cmd)| **cmd == Cmd::Flip)
+                .map(move |(i, _)| (t, i))
+        })
+        .collect::<Vec<_>>();
+    // Two Flips in a time step cancel each other, which is left as it is.
+    if flips.windows(2).any(|w| w[0].0 == w[1].0) || flips.len() % 2 != 0 {
+        return steps;
+    }
+    for pair in flips.chunks(2) {
+        let ((on, on_slot), (off, off_slot)) = (pair[0], pair[1]);
+        steps[on][on_slot] = Cmd::Wait;
+        steps[off][off_slot] = Cmd::Wait;
+        // High harmonics is needed from the first time step which ends with
+        // an ungrounded voxel.
+        let needed = match invalid_at(problem, &steps) {
+            None => continue,
+            Some(t) if on <= t && t < off => t,
+            Some(_) => {
+                steps[on][on_slot] = Cmd::Flip;
+                steps[off][off_slot] = Cmd::Flip;
+                continue;
+            }
+        };
+        let on = (on..=needed)
+            .rev()
+            .find(|t| wait_slot(&steps[*t]).is_some())
+            .unwrap();
+        let slot = wait_slot(&steps[on]).unwrap();
+        steps[on][slot] = Cmd::Flip;
+
+        // The earliest time step to turn it off. Later ones are valid if an
+        // earlier one is, and the original one is.
+        let candidates = (on + 1..=off)
+            .filter(|t| wait_slot(&steps[*t]).is_some())
+            .collect::<Vec<_>>();
+        let with_off = |steps: &mut Steps, t: usize| {
+            let slot = wait_slot(&steps[t]).unwrap();
+            steps[t][slot] = Cmd::Flip;
+            let valid = invalid_at(problem, steps).is_none();
+            steps[t][slot] = Cmd::Wait;
+            valid
+        };
+        let (mut lo, mut hi) = (0, candidates.len() - 1);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if with_off(&mut steps, candidates[mid]) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        let off = candidates[lo];
+        let slot = wait_slot(&steps[off]).unwrap();
+        steps[off][slot] = Cmd::Flip;
+    }
+    steps
+}
+
+// A single bot fills, so it is at the same position as in the next such step.
+fn is_fill_step(step: &[Cmd]) -> bool {
+    matches!(step, [Cmd::Fill(_)])
+}
+
+fn reorder_fills(problem: &Problem, steps: &[Vec<Cmd>]) -> Steps {
+    let mut steps = steps.to_vec();
+    let mut t = 0;
+    while t < steps.len() {
+        let end = (t..steps.len())
+            .find(|i| !is_fill_step(&steps[*i]))
+            .unwrap_or(steps.len());
+        steps[t..end].sort_by_key(|step| match step[0] {
+            Cmd::Fill(nd) => nd.0.dy,
+            _ => unreachable!(),
+        });
+        t = end + 1;
+    }
+    shrink_high(problem, &steps)
+}
+
+fn drop_waits(_: &Problem, steps: &[Vec<Cmd>]) -> Steps {
+    steps
+        .iter()
+        .filter(|step| step.iter().any(|cmd| *cmd != Cmd::Wait))
+        .cloned()
+        .collect()
+}
+
+fn axis(d: CordDiff) -> usize {
+    [d.dx, d.dy, d.dz].iter().position(|d| *d != 0).unwrap()
+}
+
+// One move for the legs `a` and `b`, if it passes the same voxels.
+fn merge_legs(a: CordDiff, b: CordDiff) -> Option<Cmd> {
+    let total = CordDiff::new(a.dx + b.dx, a.dy + b.dy, a.dz + b.dz);
+    if axis(a) == axis(b) {
+        if total.is_long_linear() {
+            Some(Cmd::SMove(LongLinear(total)))
+        } else {
+            None
+        }
+    } else if a.is_short_linear() && b.is_short_linear() {
+        Some(Cmd::LMove(ShortLinear(a), ShortLinear(b)))
+    } else {
+        None
+    }
+}
+
+fn merge_moves(_: &Problem, steps: &[Vec<Cmd>]) -> Steps {
+    use self::Cmd::*;
+
+    let mut res = vec![];
+    let mut legs = vec![];
+    let flush = |res: &mut Steps, legs: &mut Vec<CordDiff>| {
+        let mut prev: Option<CordDiff> = None;
+        for leg in legs.drain(..) {
+            prev = match prev {
+                None => Some(leg),
+                Some(a) => match merge_legs(a, leg) {
+                    Some(SMove(lld)) => Some(lld.0),
+                    Some(cmd) => {
+                        res.push(vec![cmd]);
+                        None
+                    }
+                    None => {
+                        res.push(vec![SMove(LongLinear(a))]);
+                        Some(leg)
+                    }
+                },
+            };
+        }
+        res.extend(prev.map(|d| vec![SMove(LongLinear(d))]));
+    };
+    for step in steps {
+        match step.as_slice() {
+            [SMove(lld)] => legs.push(lld.0),
+            [LMove(sld1, sld2)] => legs.extend(&[sld1.0, sld2.0]),
+            _ => {
+                flush(&mut res, &mut legs);
+                res.push(step.clone());
+            }
+        }
+    }
+    flush(&mut res, &mut legs);
+    res
+}
+
+pub struct Optimized {
+    pub trace: Trace,
+    pub before: SimulateResult,
+    pub after: SimulateResult,
+}
+
+/// Applies rewrites to a valid trace of `problem`, and returns the best trace
+/// which the simulator accepts.
+pub fn optimize(problem: &Problem, trace: &Trace) -> Result<Optimized> {
+    let before = simulator::verify(problem, trace)?;
+    let mut steps = to_steps(trace);
+    let mut after = before;
+    let rewrites: [Rewrite; 4] = [shrink_high, reorder_fills, drop_waits, merge_moves];
+    for rewrite in rewrites.iter() {
+        let candidate = rewrite(problem, &steps);
+        if let Ok(result) = simulator::verify(problem, &to_trace(&candidate)) {
+            if result.energy < after.energy {
+                steps = candidate;
+                after = result;
+            }
+        }
+    }
+    Ok(Optimized {
+        trace: to_trace(&steps),
+        before,
+        after,
+    })
+}
+
+/// Optimizes the trace file for the model files, and writes the result to
+/// `output`.
+pub fn write_optimized(
+    src: Option<String>,
+    tgt: Option<String>,
+    trace: String,
+    output: &Path,
+) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let optimized = optimize(&problem, &Trace::read(&trace)?)?;
+    let (before, after) = (optimized.before, optimized.after);
+    println!(
+        "energy: {} -> {} ({:+.2}%), time steps: {} -> {}, commands: {} -> {}",
+        before.energy,
+        after.energy,
+        100.0 * (after.energy - before.energy) as f64 / before.energy.max(1) as f64,
+        before.time_steps,
+        after.time_steps,
+        before.cmds,
+        after.cmds
+    );
+    optimized.trace.write_to(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nanobot::model::*;
+
+    fn pillar() -> Problem {
+        let targets = vec![Cord::new(1, 0, 1), Cord::new(1, 1, 1)]
+            .into_iter()
+            .collect();
+        Model::from_cords(ModelId::Assemble(None), 3, targets).into()
+    }
+
+    fn smove(dx: i32, dy: i32, dz: i32) -> Cmd {
+        Cmd::SMove(LongLinear(CordDiff::new(dx, dy, dz)))
+    }
+
+    fn fill(dx: i32, dy: i32, dz: i32) -> Cmd {
+        Cmd::Fill(Near(CordDiff::new(dx, dy, dz)))
+    }
+
+    #[test]
+    fn optimize_test() {
+        use self::Cmd::*;
+
+        // High harmonics is not needed at all.
+        let trace = Trace {
+            cmds: vec![
+                Flip,
+                Wait,
+                smove(0, 1, 0),
+                smove(0, 0, 1),
+                fill(1, -1, 0),
+                fill(1, 0, 0),
+                smove(0, 0, -1),
+                smove(0, -1, 0),
+                Flip,
+                Halt,
+            ],
+        };
+        let problem = pillar();
+        let optimized = optimize(&problem, &trace).unwrap();
+        assert_eq!(
+            optimized.trace.cmds,
+            vec![
+                LMove(
+                    ShortLinear(CordDiff::new(0, 1, 0)),
+                    ShortLinear(CordDiff::new(0, 0, 1))
+                ),
+                fill(1, -1, 0),
+                fill(1, 0, 0),
+                LMove(
+                    ShortLinear(CordDiff::new(0, 0, -1)),
+                    ShortLinear(CordDiff::new(0, -1, 0))
+                ),
+                Halt,
+            ]
+        );
+        assert_eq!(optimized.before.time_steps, 10);
+        assert_eq!(optimized.after.time_steps, 5);
+        assert!(optimized.after.energy < optimized.before.energy);
+
+        // The top voxel is filled first, and floats for a time step.
+        let steps = vec![
+            vec![Flip],
+            vec![smove(0, 1, 0)],
+            vec![Wait],
+            vec![smove(0, 0, 1)],
+            vec![fill(1, 0, 0)],
+            vec![fill(1, -1, 0)],
+            vec![Wait],
+            vec![smove(0, 0, -1)],
+            vec![smove(0, -1, 0)],
+            vec![Flip],
+            vec![Halt],
+        ];
+        let shrunk = shrink_high(&problem, &steps);
+        let flips = shrunk
+            .iter()
+            .enumerate()
+            .filter(|(_, step)| step[0] == Flip)
+            .map(|(t, _)| t)
+            .collect::<Vec<_>>();
+        assert_eq!(flips, vec![2, 6]);
+        assert_eq!(invalid_at(&problem, &shrunk), None);
+
+        // The bottom voxel is filled first, and High harmonics is not needed.
+        let optimized = optimize(&problem, &to_trace(&steps)).unwrap();
+        assert_eq!(optimized.after.time_steps, 5);
+        assert!(!optimized.trace.cmds.contains(&Flip));
+
+        // Moves which cancel out are not merged, and the others still are.
+        let trace = Trace {
+            cmds: vec![
+                smove(0, 1, 0),
+                smove(0, 0, 1),
+                smove(0, 0, -1),
+                smove(0, 0, 1),
+                fill(1, -1, 0),
+                fill(1, 0, 0),
+                smove(0, 0, -1),
+                smove(0, -1, 0),
+                Halt,
+            ],
+        };
+        let optimized = optimize(&problem, &trace).unwrap();
+        assert_eq!(optimized.after.time_steps, 7);
+        assert_eq!(
+            optimized.trace.cmds[1..3],
+            [smove(0, 0, -1), smove(0, 0, 1)]
+        );
+
+        assert!(optimize(&problem, &Trace { cmds: vec![Halt] }).is_err());
+    }
+}
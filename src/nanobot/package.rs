@@ -0,0 +1,255 @@
+// Packages the best trace of every problem into a zip archive for submission.
+//
+// A trace is taken from the submit ledger, or from `trace/default/` if the
+// ledger has none. Every trace is validated with the simulator first.
+
+use zip;
+
+use std;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::contest;
+use super::ledger::*;
+use super::prelude::*;
+use super::problem::*;
+use super::simulator;
+use super::trace::*;
+
+#[derive(Fail, Debug)]
+#[fail(display = "Invalid package: model: {}, {}", model_name, reason)]
+pub struct InvalidPackage {
+    pub model_name: String,
+    pub reason: String,
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "Missing traces: {}", _0)]
+pub struct MissingTraces(pub String);
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TraceSource {
+    Ledger,
+    Default,
+}
+
+pub struct PackageEntry {
+    pub model_name: String,
+    pub source: TraceSource,
+    pub energy: i64,
+    pub bytes: Vec<u8>,
+}
+
+pub struct Package {
+    pub entries: Vec<PackageEntry>,
+    /// Problems which have no trace.
+    pub missing: Vec<String>,
+}
+
+fn invalid(model_name: &str, reason: impl Into<String>) -> InvalidPackage {
+    InvalidPackage {
+        model_name: model_name.to_string(),
+        reason: reason.into(),
+    }
+}
+
+fn ledger_entry(files: &ProblemFiles, ledger: &Ledger) -> Result<Option<PackageEntry>> {
+    let best = match ledger.best(&files.name) {
+        Some(best) => best,
+        None => return Ok(None),
+    };
+    let path = ledger.trace_path(&files.name);
+    if !path.exists() {
+        return Err(invalid(&files.name, format!("{} is missing", path.display())).into());
+    }
+    let bytes = std::fs::read(&path)?;
+    if let Some(sha256) = best.sha256.as_ref() {
+        if *sha256 != sha256_hex(&bytes) {
+            return Err(invalid(&files.name, "SHA-256 differs from the ledger").into());
+        }
+    }
+    let result = simulator::verify(&files.read()?, &Trace::decode(&bytes)?)?;
+    if result.energy != best.energy {
+        return Err(invalid(
+            &files.name,
+            format!(
+                "energy: {}, but the ledger has {}",
+                result.energy, best.energy
+            ),
+        )
+        .into());
+    }
+    Ok(Some(PackageEntry {
+        model_name: files.name.clone(),
+        source: TraceSource::Ledger,
+        energy: result.energy,
+        bytes,
+    }))
+}
+
+fn default_entry(files: &ProblemFiles, default_dir: &Path) -> Result<Option<PackageEntry>> {
+    let path = default_dir.join(format!("{}.nbt", files.name));
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(&path)?;
+    let result = simulator::verify(&files.read()?, &Trace::decode(&bytes)?)?;
+    Ok(Some(PackageEntry {
+        model_name: files.name.clone(),
+        source: TraceSource::Default,
+        energy: result.energy,
+        bytes,
+    }))
+}
+
+impl Package {
+    /// Collects and validates a trace for every problem in `problems`.
+    pub fn collect(
+        problems: &[ProblemFiles],
+        ledger: &Ledger,
+        default_dir: &Path,
+    ) -> Result<Package> {
+        let mut entries = vec![];
+        let mut missing = vec![];
+        for files in problems {
+            let entry = match ledger_entry(files, ledger)? {
+                Some(entry) => Some(entry),
+                None => default_entry(files, default_dir)?,
+            };
+            match entry {
+                Some(entry) => entries.push(entry),
+                None => missing.push(files.name.clone()),
+            }
+        }
+        Ok(Package { entries, missing })
+    }
+
+    /// Writes the zip archive and `<path>.sha256` next to it, in the format
+    /// of `shasum -a 256`. Returns the SHA-256 of the archive.
+    pub fn write(&self, path: &Path) -> Result<String> {
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            // A fixed time makes the archive reproducible.
+            .last_modified_time(zip::DateTime::default());
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        for entry in &self.entries {
+            zip.start_file(format!("{}.nbt", entry.model_name), options)?;
+            zip.write_all(&entry.bytes)?;
+        }
+        let bytes = zip.finish()?.into_inner();
+        let sha256 = sha256_hex(&bytes);
+        std::fs::write(path, &bytes)?;
+        let filename = path.file_name().unwrap().to_string_lossy();
+        let mut sha256_path = path.as_os_str().to_owned();
+        sha256_path.push(".sha256");
+        std::fs::write(sha256_path, format!("{}  {}\n", sha256, filename))?;
+        Ok(sha256)
+    }
+}
+
+pub fn package(output: Option<PathBuf>, allow_missing: bool) -> Result<()> {
+    let problems = discover_problems(contest::problems_dir())?;
+    let ledger = Ledger::read()?;
+    let package = Package::collect(&problems, &ledger, &contest::default_trace_dir())?;
+    for entry in &package.entries {
+        info!(
+            "model: {}, source: {:?}, energy: {}",
+            entry.model_name, entry.source, entry.energy
+        );
+    }
+    let defaults = package
+        .entries
+        .iter()
+        .filter(|e| e.source == TraceSource::Default)
+        .count();
+    println!(
+        "problems: {}, traces: {} ({} default), missing: {}",
+        problems.len(),
+        package.entries.len(),
+        defaults,
+        package.missing.len()
+    );
+    if !package.missing.is_empty() {
+        println!("missing: {}", package.missing.join(" "));
+        if !allow_missing {
+            return Err(MissingTraces(package.missing.join(", ")).into());
+        }
+    }
+    let output = output.unwrap_or_else(|| contest::submit_dir().join("trace.zip"));
+    let sha256 = package.write(&output)?;
+    println!("{}  {}", sha256, output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nanobot::ai::*;
+    use nanobot::deadline::*;
+    use nanobot::model::*;
+    use nanobot::run::solve;
+    use std::io::Read;
+
+    #[test]
+    fn package_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-package-{}", std::process::id()));
+        let model_dir = dir.join("model");
+        let default_dir = dir.join("default");
+        std::fs::create_dir_all(&model_dir).unwrap();
+        std::fs::create_dir_all(&default_dir).unwrap();
+
+        // FA001 is solved, FA002 has only a default trace, FA003 has nothing.
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let model = Model::from_cords(ModelId::Assemble(None), 6, targets);
+        for name in &["FA001", "FA002", "FA003"] {
+            let path = model_dir.join(format!("{}_tgt.mdl", name));
+            std::fs::write(path, model.encode()).unwrap();
+        }
+        let problems = discover_problems(&model_dir).unwrap();
+        let problem = problems[0].read().unwrap();
+        let run_result = solve(&problem, Ai::Many(2).into(), &Deadline::none()).unwrap();
+        let mut ledger = Ledger::read_from(dir.join("submit")).unwrap();
+        let result = Ledger::verify(&problem, &run_result).unwrap();
+        assert!(ledger.accept(&problem, &run_result, &result).unwrap());
+        run_result
+            .trace
+            .write_to(default_dir.join("FA002.nbt"))
+            .unwrap();
+
+        let package = Package::collect(&problems, &ledger, &default_dir).unwrap();
+        let sources = package
+            .entries
+            .iter()
+            .map(|e| (e.model_name.as_str(), e.source))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![
+                ("FA001", TraceSource::Ledger),
+                ("FA002", TraceSource::Default)
+            ]
+        );
+        assert_eq!(package.missing, vec!["FA003"]);
+
+        let path = dir.join("trace.zip");
+        let sha256 = package.write(&path).unwrap();
+        assert_eq!(sha256, sha256_hex(&std::fs::read(&path).unwrap()));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("trace.zip.sha256")).unwrap(),
+            format!("{}  trace.zip\n", sha256)
+        );
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut bytes = vec![];
+        archive
+            .by_name("FA001.nbt")
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, run_result.trace.encode());
+
+        // A trace which does not reproduce the energy in the ledger.
+        std::fs::write(ledger.trace_path("FA001"), &bytes[..bytes.len() - 1]).unwrap();
+        assert!(Package::collect(&problems, &ledger, &default_dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+// Trace playback as JSON lines, for viewers and notebooks which do not
+// simulate traces themselves. The first line is the initial state:
+//
+//   {"type":"Start","r":3,"full":[[1,0,1]]}
+//
+// followed by a line per time step:
+//
+//   {"type":"Step","time_step":0,"bots":[{"bid":1,"pos":[0,0,0],"cmd":"Fill 1,0,0"}],
+//    "filled":[[1,0,0]],"voided":[],"energy":101,"harmonics":"Low"}
+//
+// Bots are at the positions where they get the commands. The energy and the
+// harmonics are the ones at the end of the time step. If the trace is
+// invalid, the last line is {"type":"Error","message":"..."}.
+
+use serde_json;
+
+use std;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::bot::*;
+use super::model::*;
+use super::prelude::*;
+use super::problem::*;
+use super::simulator;
+use super::trace::*;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct BotFrame {
+    pub bid: BotId,
+    pub pos: [i32; 3],
+    pub cmd: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum Frame {
+    Start {
+        r: usize,
+        full: Vec<[i32; 3]>,
+    },
+    Step {
+        time_step: usize,
+        bots: Vec<BotFrame>,
+        filled: Vec<[i32; 3]>,
+        voided: Vec<[i32; 3]>,
+        energy: i64,
+        harmonics: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+fn xyz(c: Cord) -> [i32; 3] {
+    [c.x, c.y, c.z]
+}
+
+/// Simulates `trace` and passes each frame to `f`. An invalid trace ends with
+/// an `Error` frame, and fails.
+pub fn play(
+    src: Option<&Model>,
+    tgt: Option<&Model>,
+    trace: &Trace,
+    mut f: impl FnMut(&Frame) -> Result<()>,
+) -> Result<()> {
+    let mut sim = simulator::start(src, tgt)?;
+    let r = sim.r as i32;
+    let full = (0..r)
+        .flat_map(|x| (0..r).flat_map(move |y| (0..r).map(move |z| Cord::new(x, y, z))))
+        .filter(|c| sim.matrix[*c])
+        .map(xyz)
+        .collect();
+    f(&Frame::Start { r: sim.r, full })?;
+
+    let mut bots = vec![];
+    for cmd in &trace.cmds {
+        let bot = sim.bots.get(sim.bot_index).map(|b| BotFrame {
+            bid: b.bid,
+            pos: xyz(b.pos),
+            cmd: cmd.to_string(),
+        });
+        let time_step = sim.time_step;
+        if let Err(e) = sim.execute(*cmd) {
+            f(&Frame::Error {
+                message: e.to_string(),
+            })?;
+            return Err(e);
+        }
+        bots.extend(bot);
+        if sim.bot_index == 0 {
+            f(&Frame::Step {
+                time_step,
+                bots: std::mem::take(&mut bots),
+                filled: sim.step_filled().iter().map(|c| xyz(*c)).collect(),
+                voided: sim.step_voided().iter().map(|c| xyz(*c)).collect(),
+                energy: sim.energy,
+                harmonics: format!("{:?}", sim.harmonics),
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the playback of the trace file to `output`, or to the stdout.
+pub fn write_playback(
+    src: Option<String>,
+    tgt: Option<String>,
+    trace: String,
+    output: Option<&Path>,
+) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let trace = Trace::read(&trace)?;
+    let mut writer: Box<dyn Write> = match output {
+        Some(output) => Box::new(std::io::BufWriter::new(std::fs::File::create(output)?)),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout())),
+    };
+    let result = play(
+        problem.src.as_ref(),
+        problem.tgt.as_ref(),
+        &trace,
+        |frame| {
+            serde_json::to_writer(&mut writer, frame)?;
+            writeln!(writer)?;
+            Ok(())
+        },
+    );
+    writer.flush()?;
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn play_test() {
+        use self::Cmd::*;
+
+        let targets = vec![Cord::new(1, 0, 1), Cord::new(1, 1, 1)]
+            .into_iter()
+            .collect();
+        let src = Model::from_cords(ModelId::Disassemble(None), 3, targets);
+        let trace = Trace {
+            cmds: vec![
+                SMove(LongLinear(CordDiff::new(0, 0, 1))),
+                Fission(Near(CordDiff::new(0, 1, 0)), 0),
+                Void(Near(CordDiff::new(1, 0, 0))),
+                Void(Near(CordDiff::new(1, 0, 0))),
+                Halt,
+            ],
+        };
+        let mut frames = vec![];
+        let result = play(Some(&src), None, &trace, |frame| {
+            frames.push(serde_json::to_string(frame)?);
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(frames.len(), 5);
+        assert_eq!(
+            frames[0],
+            r#"{"type":"Start","r":3,"full":[[1,0,1],[1,1,1]]}"#
+        );
+        assert!(frames[1].starts_with(
+            r#"{"type":"Step","time_step":0,"bots":[{"bid":1,"pos":[0,0,0],"cmd":"SMove z+1"}],"filled":[],"voided":[]"#
+        ));
+        assert!(frames[3].contains(r#""bots":[{"bid":1,"pos":[0,0,1],"cmd":"Void 1,0,0"},{"bid":2,"pos":[0,1,1],"cmd":"Void 1,0,0"}]"#));
+        assert!(frames[3].contains(r#""voided":[[1,0,1],[1,1,1]]"#));
+        assert!(frames[4].starts_with(r#"{"type":"Error","message":"Invalid trace: time step: 3"#));
+    }
+}
@@ -42,7 +42,7 @@ impl Cord {
             && (self.z as usize) < r
     }
 
-    pub fn to_linear_index(&self, r: usize) -> usize {
+    pub fn to_linear_index(self, r: usize) -> usize {
         r * r * self.x as usize + r * self.y as usize + self.z as usize
     }
 
@@ -86,9 +86,11 @@ impl CordDiff {
     }
 
     pub fn is_linear(&self) -> bool {
-        self.dx != 0 && self.dy == 0 && self.dz == 0
-            || self.dx == 0 && self.dy != 0 && self.dz == 0
-            || self.dx == 0 && self.dy == 0 && self.dz != 0
+        [self.dx, self.dy, self.dz]
+            .iter()
+            .filter(|d| **d != 0)
+            .count()
+            == 1
     }
 
     pub fn is_short_linear(&self) -> bool {
@@ -201,11 +203,11 @@ impl CordDiff {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct LongLinear(pub CordDiff);
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct ShortLinear(pub CordDiff);
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Near(pub CordDiff);
 
 // Details > Regions
@@ -258,6 +260,28 @@ impl Range {
     }
 }
 
+/// Matches `s` against a glob `pattern`, in which `*` matches any string and
+/// `?` matches any character.
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let p = pattern.chars().collect::<Vec<_>>();
+    let s = s.chars().collect::<Vec<_>>();
+    // matched[j]: whether the pattern so far matches s[..j].
+    let mut matched = vec![false; s.len() + 1];
+    matched[0] = true;
+    for c in p {
+        let mut next = vec![false; s.len() + 1];
+        for j in 0..=s.len() {
+            next[j] = match c {
+                '*' => matched[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matched[j - 1],
+                c => j > 0 && matched[j - 1] && s[j - 1] == c,
+            };
+        }
+        matched = next;
+    }
+    matched[s.len()]
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -301,4 +325,16 @@ mod test {
         assert!(!d009.is_near());
     }
 
+
+    #[test]
+    fn glob_match_test() {
+        assert!(glob_match("FA0*", "FA001"));
+        assert!(glob_match("F?00*", "FD001"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("Many(2)", "Many(2)"));
+        assert!(glob_match("Many(*)", "Many(12)"));
+        assert!(!glob_match("FA0*", "FD001"));
+        assert!(!glob_match("FA00?", "FA0010"));
+        assert!(!glob_match("Many(2)", "Many(20)"));
+    }
 }
@@ -0,0 +1,214 @@
+use std;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::ledger::sha256_hex;
+use super::model::*;
+use super::prelude::*;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ProblemKind {
+    Assemble,
+    Disassemble,
+    Reassemble,
+}
+
+// Model files of a problem, e.g. `FR001_src.mdl` and `FR001_tgt.mdl`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProblemFiles {
+    pub name: String,
+    pub src: Option<PathBuf>,
+    pub tgt: Option<PathBuf>,
+}
+
+pub struct Problem {
+    pub name: String,
+    pub src: Option<Model>,
+    pub tgt: Option<Model>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Side {
+    Src,
+    Tgt,
+}
+
+// "FA001_tgt.mdl" -> ("FA001", Tgt). The name is two letters, the second one
+// of which is A, D or R, followed by digits.
+fn parse_model_filename(filename: &str) -> Option<(String, Side)> {
+    let (name, side) = if let Some(name) = filename.strip_suffix("_src.mdl") {
+        (name, Side::Src)
+    } else if let Some(name) = filename.strip_suffix("_tgt.mdl") {
+        (name, Side::Tgt)
+    } else {
+        return None;
+    };
+    let bytes = name.as_bytes();
+    if bytes.len() > 2
+        && bytes[0].is_ascii_uppercase()
+        && b"ADR".contains(&bytes[1])
+        && bytes[2..].iter().all(|b| b.is_ascii_digit())
+    {
+        Some((name.to_string(), side))
+    } else {
+        None
+    }
+}
+
+/// Finds every problem in `dir`, sorted by name.
+pub fn discover_problems(dir: impl AsRef<Path>) -> Result<Vec<ProblemFiles>> {
+    let mut problems = BTreeMap::new();
+    for entry in std::fs::read_dir(dir.as_ref())? {
+        let path = entry?.path();
+        let parsed = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(parse_model_filename);
+        if let Some((name, side)) = parsed {
+            let files = problems
+                .entry(name.clone())
+                .or_insert_with(|| ProblemFiles {
+                    name,
+                    src: None,
+                    tgt: None,
+                });
+            match side {
+                Side::Src => files.src = Some(path),
+                Side::Tgt => files.tgt = Some(path),
+            }
+        }
+    }
+    debug!(
+        "discover_problems: {}: {}",
+        dir.as_ref().display(),
+        problems.len()
+    );
+    Ok(problems.into_values().collect())
+}
+
+impl ProblemFiles {
+    /// Files given by hand. The name is taken from the file name if it looks
+    /// like a contest one.
+    pub fn new(src: Option<PathBuf>, tgt: Option<PathBuf>) -> ProblemFiles {
+        let name = src
+            .iter()
+            .chain(tgt.iter())
+            .filter_map(|p| p.file_name().and_then(|f| f.to_str()))
+            .filter_map(parse_model_filename)
+            .map(|(name, _)| name)
+            .next();
+        let name = name.unwrap_or_else(|| match (&src, &tgt) {
+            (Some(_), Some(_)) => "Reassemble(None)".to_string(),
+            (Some(_), None) => ModelId::Disassemble(None).name(),
+            _ => ModelId::Assemble(None).name(),
+        });
+        ProblemFiles { name, src, tgt }
+    }
+
+    pub fn read(&self) -> Result<Problem> {
+        let src = match self.src.as_ref() {
+            Some(path) => Some(Model::read(
+                ModelId::Disassemble(Some(self.name.clone())),
+                path,
+            )?),
+            None => None,
+        };
+        let tgt = match self.tgt.as_ref() {
+            Some(path) => Some(Model::read(
+                ModelId::Assemble(Some(self.name.clone())),
+                path,
+            )?),
+            None => None,
+        };
+        Ok(Problem::new(self.name.clone(), src, tgt))
+    }
+
+    /// The SHA-256 of the model files.
+    pub fn sha256(&self) -> Result<String> {
+        let mut bytes = vec![];
+        for (tag, path) in &[("src", &self.src), ("tgt", &self.tgt)] {
+            if let Some(path) = path {
+                bytes.extend_from_slice(tag.as_bytes());
+                bytes.extend(std::fs::read(path)?);
+            }
+        }
+        Ok(sha256_hex(&bytes))
+    }
+}
+
+impl Problem {
+    pub fn new(name: String, src: Option<Model>, tgt: Option<Model>) -> Problem {
+        assert!(src.is_some() || tgt.is_some());
+        Problem { name, src, tgt }
+    }
+
+    pub fn kind(&self) -> ProblemKind {
+        match (&self.src, &self.tgt) {
+            (Some(_), Some(_)) => ProblemKind::Reassemble,
+            (Some(_), None) => ProblemKind::Disassemble,
+            _ => ProblemKind::Assemble,
+        }
+    }
+
+    pub fn r(&self) -> usize {
+        self.src.as_ref().or(self.tgt.as_ref()).unwrap().r
+    }
+}
+
+impl From<Model> for Problem {
+    fn from(model: Model) -> Problem {
+        let name = model.id.name();
+        match model.id {
+            ModelId::Assemble(_) => Problem::new(name, None, Some(model)),
+            ModelId::Disassemble(_) => Problem::new(name, Some(model), None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_model_filename_test() {
+        assert_eq!(
+            parse_model_filename("FA001_tgt.mdl"),
+            Some(("FA001".to_string(), Side::Tgt))
+        );
+        assert_eq!(
+            parse_model_filename("FR120_src.mdl"),
+            Some(("FR120".to_string(), Side::Src))
+        );
+        assert_eq!(
+            parse_model_filename("LA001_tgt.mdl"),
+            Some(("LA001".to_string(), Side::Tgt))
+        );
+        assert_eq!(parse_model_filename("FX001_tgt.mdl"), None);
+        assert_eq!(parse_model_filename("FA001.mdl"), None);
+        assert_eq!(parse_model_filename("FA001_tgt.nbt"), None);
+    }
+
+    #[test]
+    fn discover_problems_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-discover-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for f in &[
+            "FA002_tgt.mdl",
+            "FD001_src.mdl",
+            "FR001_src.mdl",
+            "FR001_tgt.mdl",
+            "LA001_tgt.mdl",
+            "README",
+        ] {
+            std::fs::write(dir.join(f), b"").unwrap();
+        }
+        let problems = discover_problems(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names = problems.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["FA002", "FD001", "FR001", "LA001"]);
+        assert_eq!(problems[0].src, None);
+        assert_eq!(problems[0].tgt, Some(dir.join("FA002_tgt.mdl")));
+        assert!(problems[2].src.is_some() && problems[2].tgt.is_some());
+    }
+}
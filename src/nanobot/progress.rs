@@ -0,0 +1,243 @@
+// Progress of ci, and a JSON-lines log of every task, i.e. solving a model
+// with a strategy.
+
+use chrono::{DateTime, Local};
+use serde_json;
+
+use std;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::ai::*;
+use super::prelude::*;
+use super::run::RunResult;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskRecord {
+    /// When ci started, which tells runs apart.
+    pub run: String,
+    pub model_name: String,
+    pub strategy: String,
+    pub start: String,
+    pub end: String,
+    pub elapsed_millis: u64,
+    pub energy: Option<i64>,
+    pub time_steps: Option<usize>,
+    pub cmds: usize,
+    /// The peak resident set size of the whole process so far, in kB. Tasks
+    /// run in parallel, so it is not the one of the task alone.
+    pub peak_memory_kb: Option<u64>,
+}
+
+pub struct Task {
+    model_name: String,
+    strategy: Strategy,
+    start: DateTime<Local>,
+}
+
+struct State {
+    total: usize,
+    done: usize,
+    solved: usize,
+    running: BTreeSet<(String, String)>,
+}
+
+pub struct Progress {
+    run: DateTime<Local>,
+    start: Instant,
+    state: Mutex<State>,
+    log: Option<Mutex<File>>,
+}
+
+/// VmHWM in /proc/self/status.
+pub fn peak_memory_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, secs / 60 % 60)
+    } else {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+impl Progress {
+    /// Appends task records to `log` if it is given.
+    pub fn new(total: usize, log: Option<&Path>) -> Result<Progress> {
+        let log = match log {
+            Some(path) => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                Some(Mutex::new(file))
+            }
+            None => None,
+        };
+        Ok(Progress {
+            run: Local::now(),
+            start: Instant::now(),
+            state: Mutex::new(State {
+                total,
+                done: 0,
+                solved: 0,
+                running: BTreeSet::new(),
+            }),
+            log,
+        })
+    }
+
+    pub fn add_tasks(&self, n: usize) {
+        self.state.lock().unwrap().total += n;
+    }
+
+    /// Tasks which are cached or skipped.
+    pub fn skip(&self, n: usize) {
+        self.state.lock().unwrap().done += n;
+    }
+
+    pub fn start(&self, model_name: &str, strategy: Strategy) -> Task {
+        self.state
+            .lock()
+            .unwrap()
+            .running
+            .insert((model_name.to_string(), strategy.to_string()));
+        Task {
+            model_name: model_name.to_string(),
+            strategy,
+            start: Local::now(),
+        }
+    }
+
+    pub fn finish(&self, task: Task, run_result: &RunResult) -> Result<()> {
+        let end = Local::now();
+        let line = {
+            let mut state = self.state.lock().unwrap();
+            state
+                .running
+                .remove(&(task.model_name.clone(), task.strategy.to_string()));
+            state.done += 1;
+            state.solved += 1;
+            self.status_line(&state)
+        };
+        eprintln!("{}", line);
+
+        if let Some(log) = self.log.as_ref() {
+            let record = TaskRecord {
+                run: self.run.to_rfc3339(),
+                model_name: task.model_name,
+                strategy: task.strategy.to_string(),
+                start: task.start.to_rfc3339(),
+                end: end.to_rfc3339(),
+                elapsed_millis: (end - task.start).num_milliseconds().max(0) as u64,
+                energy: run_result.energy,
+                time_steps: run_result.time_steps,
+                cmds: run_result.trace.cmds.len(),
+                peak_memory_kb: peak_memory_kb(),
+            };
+            let mut log = log.lock().unwrap();
+            writeln!(log, "{}", serde_json::to_string(&record)?)?;
+        }
+        Ok(())
+    }
+
+    // e.g. "[12/340 3.5%] ETA 5m12s, running: FA001 Many(2), FA002 Many(3)"
+    fn status_line(&self, state: &State) -> String {
+        let remaining = state.total.saturating_sub(state.done);
+        let eta = if state.solved > 0 {
+            let per_task = self.start.elapsed() / state.solved as u32;
+            format_duration(per_task * remaining as u32)
+        } else {
+            "-".to_string()
+        };
+        let running = state
+            .running
+            .iter()
+            .take(4)
+            .map(|(model, strategy)| format!("{} {}", model, strategy))
+            .collect::<Vec<_>>();
+        let more = state.running.len().saturating_sub(running.len());
+        let mut line = format!(
+            "[{}/{} {:.1}%] ETA {}",
+            state.done,
+            state.total,
+            100.0 * state.done as f64 / state.total.max(1) as f64,
+            eta
+        );
+        if !running.is_empty() {
+            line += &format!(", running: {}", running.join(", "));
+        }
+        if more > 0 {
+            line += &format!(" and {} more", more);
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nanobot::trace::*;
+
+    #[test]
+    fn progress_test() {
+        let path =
+            std::env::temp_dir().join(format!("icfp2018-telemetry-{}.jsonl", std::process::id()));
+        let progress = Progress::new(4, Some(&path)).unwrap();
+        progress.skip(1);
+        let a = progress.start("FA001", Ai::Many(2).into());
+        let b = progress.start("FA002", Ai::Many(3).into());
+        {
+            let state = progress.state.lock().unwrap();
+            let line = progress.status_line(&state);
+            assert_eq!(
+                line,
+                "[1/4 25.0%] ETA -, running: FA001 Many(2), FA002 Many(3)"
+            );
+        }
+        let run_result = RunResult {
+            problem_name: "FA001".to_string(),
+            strategy: Ai::Many(2).into(),
+            energy: Some(100),
+            trace: Trace { cmds: vec![] },
+            elapsed: Duration::from_millis(1),
+            time_steps: Some(1),
+            timed_out: false,
+        };
+        progress.finish(a, &run_result).unwrap();
+        progress.finish(b, &run_result).unwrap();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records = log
+            .lines()
+            .map(|line| serde_json::from_str::<TaskRecord>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].model_name, "FA002");
+        assert_eq!(records[1].strategy, "Many(3)");
+        assert_eq!(records[0].energy, Some(100));
+        // Only Linux has /proc/self/status.
+        if cfg!(target_os = "linux") {
+            assert!(records[0].peak_memory_kb.unwrap() > 0);
+        } else {
+            assert!(log.lines().all(|line| line.contains("\"peak_memory_kb\":")));
+        }
+    }
+}
@@ -0,0 +1,223 @@
+// Renders slices of a matrix as character grids, for debugging:
+//
+//   .  void
+//   #  full
+//   @  bot
+//   *  volatile void
+//   %  volatile full
+//
+// A slice is printed with the origin at the bottom left.
+
+use failure;
+
+use std;
+use std::path::PathBuf;
+
+use super::matrix::*;
+use super::prelude::*;
+use super::problem::*;
+use super::simulator;
+use super::trace::*;
+
+pub const LEGEND: &str = ". void, # full, @ bot, * volatile void, % volatile full";
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl std::str::FromStr for Axis {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Axis> {
+        match s {
+            "x" => Ok(Axis::X),
+            "y" => Ok(Axis::Y),
+            "z" => Ok(Axis::Z),
+            _ => bail!("Invalid axis: {}", s),
+        }
+    }
+}
+
+impl Axis {
+    pub fn name(self) -> &'static str {
+        match self {
+            Axis::X => "x",
+            Axis::Y => "y",
+            Axis::Z => "z",
+        }
+    }
+
+    // The axes of columns and rows.
+    pub fn plane(self) -> (Axis, Axis) {
+        match self {
+            Axis::X => (Axis::Z, Axis::Y),
+            Axis::Y => (Axis::X, Axis::Z),
+            Axis::Z => (Axis::X, Axis::Y),
+        }
+    }
+
+    pub fn cord(self, index: i32, col: i32, row: i32) -> Cord {
+        match self {
+            Axis::X => Cord::new(index, row, col),
+            Axis::Y => Cord::new(col, index, row),
+            Axis::Z => Cord::new(col, row, index),
+        }
+    }
+
+    pub fn of(self, c: Cord) -> i32 {
+        match self {
+            Axis::X => c.x,
+            Axis::Y => c.y,
+            Axis::Z => c.z,
+        }
+    }
+}
+
+/// Renders the slice of `matrix` at `index` along `axis`.
+pub fn render_slice(
+    matrix: &Matrix,
+    axis: Axis,
+    index: i32,
+    bots: &[Cord],
+    is_volatile: impl Fn(Cord) -> bool,
+) -> String {
+    let r = matrix.r as i32;
+    let (col_axis, row_axis) = axis.plane();
+    let mut text = format!(
+        "{}={} ({} right, {} up)\n",
+        axis.name(),
+        index,
+        col_axis.name(),
+        row_axis.name()
+    );
+    for row in (0..r).rev() {
+        let line = (0..r)
+            .map(|col| {
+                let c = axis.cord(index, col, row);
+                match (bots.contains(&c), is_volatile(c), matrix[c]) {
+                    (true, _, _) => '@',
+                    (false, true, false) => '*',
+                    (false, true, true) => '%',
+                    (false, false, true) => '#',
+                    (false, false, false) => '.',
+                }
+            })
+            .collect::<String>();
+        text += &line;
+        text.push('\n');
+    }
+    text
+}
+
+/// Renders every slice along `axis` which has a full voxel or a bot, or only
+/// the one at `index`.
+pub fn render_slices(
+    matrix: &Matrix,
+    axis: Axis,
+    index: Option<i32>,
+    bots: &[Cord],
+    is_volatile: impl Fn(Cord) -> bool,
+) -> String {
+    let r = matrix.r as i32;
+    let indices = match index {
+        Some(index) => vec![index],
+        None => (0..r)
+            .filter(|&i| {
+                bots.iter().any(|b| axis.of(*b) == i)
+                    || (0..r)
+                        .flat_map(|col| (0..r).map(move |row| (col, row)))
+                        .any(|(col, row)| matrix[axis.cord(i, col, row)])
+            })
+            .collect(),
+    };
+    indices
+        .into_iter()
+        .map(|i| render_slice(matrix, axis, i, bots, &is_volatile))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders a model, or the simulator state after `time_steps` time steps of
+/// `trace` if it is given.
+pub fn print_render(
+    src: Option<String>,
+    tgt: Option<String>,
+    trace: Option<String>,
+    time_steps: Option<usize>,
+    axis: Axis,
+    index: Option<i32>,
+) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let r = problem.r();
+    if let Some(index) = index {
+        if index < 0 || index as usize >= r {
+            bail!("Index out of range: {}, resolution: {}", index, r);
+        }
+    }
+    println!("{}", LEGEND);
+    let trace = match trace {
+        Some(trace) => Trace::read(trace)?,
+        None => {
+            // The target, or the source of a disassembly.
+            let model = problem.tgt.as_ref().or(problem.src.as_ref()).unwrap();
+            let matrix = Matrix::from_cords(r, &model.targets);
+            print!("{}", render_slices(&matrix, axis, index, &[], |_| false));
+            return Ok(());
+        }
+    };
+    let mut sim = simulator::start(problem.src.as_ref(), problem.tgt.as_ref())?;
+    let steps = trace.time_steps();
+    let n = time_steps.unwrap_or(steps.len()).min(steps.len());
+    for cmd in steps[..n].iter().flat_map(|cmds| cmds.iter()) {
+        sim.execute(*cmd)?;
+    }
+    println!(
+        "time step: {}, energy: {}, bots: {}",
+        sim.time_step,
+        sim.energy,
+        sim.bots.len()
+    );
+    let bots = sim.bots.iter().map(|b| b.pos).collect::<Vec<_>>();
+    print!(
+        "{}",
+        render_slices(&sim.matrix, axis, index, &bots, |c| sim.is_volatile(c))
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_slice_test() {
+        let matrix = Matrix::from_cords(3, &[Cord::new(1, 0, 1), Cord::new(2, 0, 1)]);
+        let bots = vec![Cord::new(0, 0, 0)];
+        let volatile = |c: Cord| c == Cord::new(2, 0, 1) || c == Cord::new(0, 0, 2);
+        assert_eq!(
+            render_slice(&matrix, Axis::Y, 0, &bots, volatile),
+            "y=0 (x right, z up)\n*..\n.#%\n@..\n"
+        );
+        assert_eq!(
+            render_slice(&matrix, Axis::Z, 1, &bots, |_| false),
+            "z=1 (x right, y up)\n...\n...\n.##\n"
+        );
+        assert_eq!(
+            render_slice(&matrix, Axis::X, 0, &bots, |_| false),
+            "x=0 (z right, y up)\n...\n...\n@..\n"
+        );
+
+        // Only slices which have something.
+        let text = render_slices(&matrix, Axis::X, None, &[], |_| false);
+        assert!(text.starts_with("x=1 "));
+        assert_eq!(text.matches('=').count(), 2);
+        assert_eq!("z".parse::<Axis>().unwrap(), Axis::Z);
+        assert!("w".parse::<Axis>().is_err());
+    }
+}
@@ -0,0 +1,255 @@
+// The report of a ci run, which compares every model with the best score in
+// the ledger before the run.
+
+use serde_json;
+
+use std;
+use std::fmt::Write;
+
+use super::contest;
+use super::prelude::*;
+
+#[derive(Fail, Debug)]
+#[fail(display = "Regressed models: {}", _0)]
+pub struct Regression(pub String);
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Status {
+    New,
+    Improved,
+    Same,
+    Regressed,
+    Failed,
+    /// Worse than before, or failed, without running the strategy of the best
+    /// before, e.g. because of `--strategy`.
+    Partial,
+    Unsolved,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelReport {
+    pub model_name: String,
+    pub r: usize,
+    pub best_before: Option<i64>,
+    /// Whether the strategy of `best_before` ran again. Otherwise the model
+    /// does not regress.
+    pub rerun: bool,
+    /// The best energy in this run.
+    pub best_now: Option<i64>,
+    pub strategy: Option<String>,
+    /// Strategies which failed.
+    pub failures: Vec<String>,
+    pub lower_bound: i64,
+    /// The expected score of the best energy so far.
+    pub score: Option<i64>,
+}
+
+impl ModelReport {
+    pub fn delta(&self) -> Option<i64> {
+        match (self.best_before, self.best_now) {
+            (Some(before), Some(now)) => Some(now - before),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match (self.best_before, self.best_now) {
+            (None, Some(_)) => Status::New,
+            (None, None) => Status::Unsolved,
+            (Some(before), now) if !self.rerun && now.is_none_or(|now| now > before) => {
+                Status::Partial
+            }
+            (Some(_), None) => Status::Failed,
+            (Some(before), Some(now)) if now < before => Status::Improved,
+            (Some(before), Some(now)) if now == before => Status::Same,
+            _ => Status::Regressed,
+        }
+    }
+
+    pub fn is_regression(&self) -> bool {
+        matches!(self.status(), Status::Regressed | Status::Failed)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ModelReportJson {
+    #[serde(flatten)]
+    report: ModelReport,
+    delta: Option<i64>,
+    status: Status,
+}
+
+#[derive(Debug)]
+pub struct CiReport {
+    pub models: Vec<ModelReport>,
+}
+
+fn or_dash<T: ToString>(x: Option<T>) -> String {
+    x.map_or("-".to_string(), |x| x.to_string())
+}
+
+impl CiReport {
+    pub fn new(mut models: Vec<ModelReport>) -> CiReport {
+        models.sort_by(|a, b| a.model_name.cmp(&b.model_name));
+        CiReport { models }
+    }
+
+    /// Models which regressed, or failed although they were solved before.
+    /// Only `watched` models count, or every model if it is empty.
+    pub fn regressions(&self, watched: &[String]) -> Vec<&ModelReport> {
+        self.models
+            .iter()
+            .filter(|m| watched.is_empty() || watched.contains(&m.model_name))
+            .filter(|m| m.is_regression())
+            .collect()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut s = String::new();
+        writeln!(
+            s,
+            "| model | r | best before | best now | delta | status | strategy | lower bound | score | failures |"
+        )
+        .unwrap();
+        writeln!(s, "|---|--:|--:|--:|--:|---|---|--:|--:|---|").unwrap();
+        let mut total_energy = 0;
+        let mut total_lower_bound = 0;
+        let mut total_score = 0;
+        for m in &self.models {
+            writeln!(
+                s,
+                "| {} | {} | {} | {} | {} | {:?} | {} | {} | {} | {} |",
+                m.model_name,
+                m.r,
+                or_dash(m.best_before),
+                or_dash(m.best_now),
+                or_dash(m.delta()),
+                m.status(),
+                m.strategy.as_ref().map_or("-", |s| s.as_str()),
+                m.lower_bound,
+                or_dash(m.score),
+                m.failures.join(", ")
+            )
+            .unwrap();
+            if let Some(energy) = m.best_now {
+                total_energy += energy;
+                total_lower_bound += m.lower_bound;
+            }
+            total_score += m.score.unwrap_or(0);
+        }
+        writeln!(
+            s,
+            "\nTotal (solved): {}, lower bound: {}, ratio: {:.3}, score: {}",
+            total_energy,
+            total_lower_bound,
+            total_energy as f64 / total_lower_bound.max(1) as f64,
+            total_score
+        )
+        .unwrap();
+        s
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let models = self
+            .models
+            .iter()
+            .map(|m| ModelReportJson {
+                report: m.clone(),
+                delta: m.delta(),
+                status: m.status(),
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::to_string_pretty(&models)?)
+    }
+
+    /// Writes `ci.md` and `ci.json` to the report directory.
+    pub fn write(&self) -> Result<()> {
+        let dir = contest::report_dir();
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("ci.md"), self.to_markdown())?;
+        std::fs::write(dir.join("ci.json"), self.to_json()?)?;
+        info!("Wrote report to: {}", dir.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn model_report(name: &str, before: Option<i64>, now: Option<i64>) -> ModelReport {
+        ModelReport {
+            model_name: name.to_string(),
+            r: 20,
+            best_before: before,
+            rerun: true,
+            best_now: now,
+            strategy: now.map(|_| "Many(2)".to_string()),
+            failures: vec![],
+            lower_bound: 100,
+            score: now.map(|_| 1000),
+        }
+    }
+
+    #[test]
+    fn report_test() {
+        let report = CiReport::new(vec![
+            model_report("FA003", Some(200), Some(300)),
+            model_report("FA001", Some(200), Some(150)),
+            model_report("FA002", Some(200), None),
+            model_report("FA004", None, Some(200)),
+            model_report("FA005", Some(200), Some(200)),
+        ]);
+        let statuses = report.models.iter().map(|m| m.status()).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::Improved,
+                Status::Failed,
+                Status::Regressed,
+                Status::New,
+                Status::Same
+            ]
+        );
+        assert_eq!(report.models[2].delta(), Some(100));
+
+        let names = |regressions: Vec<&ModelReport>| {
+            regressions
+                .iter()
+                .map(|m| m.model_name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(report.regressions(&[])), vec!["FA002", "FA003"]);
+        assert_eq!(
+            names(report.regressions(&["FA003".to_string(), "FA004".to_string()])),
+            vec!["FA003"]
+        );
+
+        let markdown = report.to_markdown();
+        assert!(markdown
+            .contains("| FA003 | 20 | 200 | 300 | 100 | Regressed | Many(2) | 100 | 1000 |  |"));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json[1]["status"], "Failed");
+        assert_eq!(json[1]["best_now"], serde_json::Value::Null);
+
+        // The best before came from a strategy which did not run.
+        let mut partial = CiReport::new(vec![
+            model_report("FA002", Some(200), None),
+            model_report("FA003", Some(200), Some(300)),
+            model_report("FA001", Some(200), Some(150)),
+        ]);
+        for m in &mut partial.models {
+            m.rerun = false;
+        }
+        let statuses = partial
+            .models
+            .iter()
+            .map(|m| m.status())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![Status::Improved, Status::Partial, Status::Partial]
+        );
+        assert!(partial.regressions(&[]).is_empty());
+    }
+}
@@ -1,194 +1,524 @@
-use serde_json;
-
-use std;
-use std::collections::HashMap;
+use std::cmp::Reverse;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use super::ai::*;
+use super::bot::*;
+use super::cache::*;
+use super::cluster::*;
+use super::contest;
+use super::deadline::*;
+use super::ledger::*;
 use super::model::*;
 use super::prelude::*;
+use super::problem::*;
+use super::progress::*;
+use super::report::*;
+use super::score::*;
+use super::simulator::{self, SimulateResult};
 use super::system::*;
 use super::trace::*;
 use rayon::prelude::*;
 
 pub struct RunResult {
-    pub ai: Ai,
+    pub problem_name: String,
+    pub strategy: Strategy,
     pub energy: Option<i64>,
-    pub system: System,
+    pub trace: Trace,
+    pub elapsed: Duration,
+    /// Set when the trace is verified by the simulator.
+    pub time_steps: Option<usize>,
+    /// Set when the strategy failed because its time budget expired.
+    pub timed_out: bool,
 }
 
 impl RunResult {
-    pub fn trace(&self) -> Trace {
-        Trace {
-            cmds: self.system.records.clone(),
-        }
-    }
-
     pub fn model_name(&self) -> String {
-        self.system.model_id.name()
+        self.problem_name.clone()
     }
 
     pub fn write_trace(&self) -> Result<()> {
-        self.trace().write_to_trace_dir(&format!(
-            "{}-{:?}-{:?}.nbt",
+        self.trace.write_to_trace_dir(&format!(
+            "{}-{}-{:?}.nbt",
             self.model_name(),
-            self.ai,
+            self.strategy,
             self.energy
         ))
     }
 }
 
+pub struct Portfolio {
+    pub results: Vec<RunResult>,
+}
+
+impl Portfolio {
+    /// The result which has the lowest energy, if any AI succeeded.
+    pub fn best(&self) -> Option<&RunResult> {
+        self.results
+            .iter()
+            .filter(|r| r.energy.is_some())
+            .min_by_key(|r| r.energy.unwrap())
+    }
+}
+
 pub fn run(
-    bots: Option<usize>,
+    bots: Vec<usize>,
     src: Option<String>,
     target: Option<String>,
     output: Option<String>,
+    timeout: Option<Duration>,
+    seed: u64,
+    seeds: usize,
 ) -> Result<()> {
-    let model = if let Some(src) = src {
-        Model::read(ModelId::Disassemble(None), src)?
-    } else if let Some(target) = target {
-        Model::read(ModelId::Assemble(None), target)?
+    if src.is_none() && target.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), target.map(PathBuf::from)).read()?;
+
+    let ais = if bots.is_empty() {
+        vec![Ai::Many(2)]
     } else {
-        unreachable!()
+        bots.into_iter().map(Ai::Many).collect()
     };
-
-    let run_result = solve(&model, Ai::Many(bots.unwrap_or(2)))?;
-    if let Some(energy) = run_result.energy.as_ref() {
-        println!("{}", energy);
-        info!("trace: len: {}", run_result.system.records.len())
+    let strategies = Strategy::with_seeds(&ais, seed, seeds);
+    let portfolio = solve_portfolio(&problem, &strategies, &Deadline::after(timeout))?;
+    for run_result in &portfolio.results {
+        info!(
+            "strategy: {}, energy: {:?}, trace: len: {}",
+            run_result.strategy,
+            run_result.energy,
+            run_result.trace.cmds.len()
+        );
+    }
+    if let Some(run_result) = portfolio.best() {
+        let energy = run_result.energy.unwrap();
+        let lower_bound = problem.energy_lower_bound();
+        println!(
+            "{} (lower bound: {}, ratio: {:.3})",
+            energy,
+            lower_bound,
+            energy as f64 / lower_bound as f64
+        );
+        if let Some(output) = output {
+            info!("Writing trace to: {}", output);
+            run_result.trace.write_to(output)?;
+        }
     } else {
         eprintln!("failed");
-        info!("trace: len: {}", run_result.system.records.len())
-    }
-    if let Some(output) = output {
-        info!("Writing trace to: {}", output);
-        run_result.trace().write_to(output)?;
     }
     Ok(())
 }
 
-pub fn solve(model: &Model, ai: Ai) -> Result<RunResult> {
-    let model_id = model.id;
+// Returns the trace even if the AI fails, for debugging.
+fn solve_model(model: &Model, strategy: Strategy, deadline: &Deadline) -> (Result<i64>, Trace) {
     let mut system = System::new(model);
-    let result = match ai {
-        Ai::Many(bots) => Many::new(bots).solve(&mut system),
+    let result = match strategy.ai {
+        Ai::Many(bots) => Many::new(bots, strategy.seed).solve(&mut system, deadline),
+        Ai::Default => Sweep::new(model).solve(&mut system, deadline),
+    };
+    let energy = system.energy;
+    let trace = Trace {
+        cmds: system.records,
+    };
+    (result.map(|_| energy), trace)
+}
+
+pub fn solve(problem: &Problem, strategy: Strategy, deadline: &Deadline) -> Result<RunResult> {
+    let start = Instant::now();
+    let (result, trace) = match (problem.src.as_ref(), problem.tgt.as_ref()) {
+        (Some(src), Some(tgt)) => {
+            // Disassemble everything, then assemble. The Halt step of the
+            // first half is dropped.
+            let (result, mut trace) = solve_model(src, strategy, deadline);
+            match result {
+                Ok(energy) => {
+                    assert_eq!(trace.cmds.pop(), Some(Cmd::Halt));
+                    let r = problem.r() as i64;
+                    let halt_energy = 3 * r * r * r + 20;
+                    let (result, tgt_trace) = solve_model(tgt, strategy, deadline);
+                    trace.cmds.extend(tgt_trace.cmds);
+                    (result.map(|e| energy - halt_energy + e), trace)
+                }
+                Err(e) => (Err(e), trace),
+            }
+        }
+        (Some(model), None) | (None, Some(model)) => solve_model(model, strategy, deadline),
+        (None, None) => unreachable!(),
     };
     match result {
-        Ok(_) => Ok(RunResult {
-            energy: Some(system.energy),
-            ai,
-            system,
+        Ok(energy) => Ok(RunResult {
+            problem_name: problem.name.clone(),
+            strategy,
+            energy: Some(energy),
+            trace,
+            elapsed: start.elapsed(),
+            time_steps: None,
+            timed_out: false,
         }),
-        Err(_) => {
-            warn!("Failed to solve: model: {}, ai: {:?}", model_id.name(), ai);
+        Err(e) => {
+            warn!(
+                "Failed to solve: model: {}, strategy: {}: {}",
+                problem.name, strategy, e
+            );
             Ok(RunResult {
+                problem_name: problem.name.clone(),
+                strategy,
                 energy: None,
-                ai,
-                system,
+                trace,
+                elapsed: start.elapsed(),
+                time_steps: None,
+                timed_out: e.downcast_ref::<Timeout>().is_some(),
             })
         }
     }
 }
 
-// contest/submit/submit.json
-#[derive(Serialize, Deserialize, Debug)]
-struct Submit {
-    best_scores: HashMap<String, BestScore>,
+// Simulates the encoded trace with the standalone simulator and checks that it
+// reproduces the energy which the AI claims.
+fn verify_run_result(problem: &Problem, run_result: &RunResult) -> Result<SimulateResult> {
+    let trace = Trace::decode(&run_result.trace.encode())?;
+    let result = simulator::verify(problem, &trace)?;
+    if Some(result.energy) != run_result.energy {
+        warn!(
+            "Energy mismatch: model: {}, strategy: {}, energy: {:?}, simulated: {}",
+            run_result.model_name(),
+            run_result.strategy,
+            run_result.energy,
+            result.energy
+        );
+        return Err(NanoBotError.into());
+    }
+    Ok(result)
+}
+
+/// Verifies the trace of `run_result`. A trace which fails verification is
+/// treated as a failure.
+pub fn verified(problem: &Problem, mut run_result: RunResult) -> RunResult {
+    if run_result.energy.is_some() {
+        match verify_run_result(problem, &run_result) {
+            Ok(result) => run_result.time_steps = Some(result.time_steps),
+            Err(e) => {
+                warn!(
+                    "Verification failed: model: {}, strategy: {}: {}",
+                    run_result.model_name(),
+                    run_result.strategy,
+                    e
+                );
+                run_result.energy = None;
+            }
+        }
+    }
+    run_result
+}
+
+fn solve_verified(problem: &Problem, strategy: Strategy, deadline: &Deadline) -> Result<RunResult> {
+    Ok(verified(problem, solve(problem, strategy, deadline)?))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct BestScore {
-    ai: String,
-    energy: i64,
+/// Solves `model` with every strategy in parallel. Each trace is verified by
+/// the simulator; a trace which fails verification is treated as a failure.
+/// Strategies which have not finished by `deadline` fail with `Timeout`.
+pub fn solve_portfolio(
+    problem: &Problem,
+    strategies: &[Strategy],
+    deadline: &Deadline,
+) -> Result<Portfolio> {
+    let results = strategies
+        .par_iter()
+        .map(|strategy| solve_verified(problem, *strategy, deadline))
+        .collect::<Result<_>>()?;
+    Ok(Portfolio { results })
 }
 
-impl Submit {
-    fn read_submit_json() -> Result<HashMap<String, BestScore>> {
-        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        path.push("contest/submit/submit.json");
-        if !path.exists() {
-            Ok(Default::default())
-        } else {
-            Ok(serde_json::from_str(&std::fs::read_to_string(path)?).unwrap())
-        }
+/// Stores `run_result` of the model files whose SHA-256 is `sha256`. A result
+/// which timed out is not stored, since it may succeed with more time.
+pub fn cache_result(cache: Option<&Cache>, sha256: &str, run_result: &RunResult) {
+    let cache = match cache {
+        Some(cache) if !run_result.timed_out => cache,
+        _ => return,
+    };
+    if let Err(e) = cache.put(sha256, run_result) {
+        warn!(
+            "Not cached: model: {}, strategy: {}: {}",
+            run_result.problem_name, run_result.strategy, e
+        );
     }
+}
 
-    fn read() -> Result<Submit> {
-        Ok(Submit {
-            best_scores: Submit::read_submit_json()?,
+// `solve_portfolio` which reports every task to `progress`, and caches it as
+// soon as it finishes.
+fn solve_portfolio_with_progress(
+    problem: &Problem,
+    strategies: &[Strategy],
+    deadline: &Deadline,
+    progress: &Progress,
+    cache: Option<&Cache>,
+    sha256: &str,
+) -> Result<Portfolio> {
+    let results = strategies
+        .par_iter()
+        .map(|strategy| {
+            let task = progress.start(&problem.name, *strategy);
+            let run_result = solve_verified(problem, *strategy, deadline)?;
+            cache_result(cache, sha256, &run_result);
+            progress.finish(task, &run_result)?;
+            Ok(run_result)
         })
-    }
+        .collect::<Result<_>>()?;
+    Ok(Portfolio { results })
+}
 
-    fn write(&self) -> Result<()> {
-        debug!("Writing submit");
-        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        path.push("contest/submit/submit.json");
-        std::fs::write(path, serde_json::to_string(&self.best_scores)?)?;
-        Ok(())
-    }
+#[derive(Default, Debug)]
+pub struct CiConfig {
+    /// Time limit for solving one model with every AI.
+    pub model_timeout: Option<Duration>,
+    /// Time limit for the whole run. Models which are not started by then are
+    /// skipped.
+    pub timeout: Option<Duration>,
+    /// Runs each AI with this many seeds, `seed`, `seed + 1`, ..., instead of
+    /// once without a seed.
+    pub seeds: usize,
+    pub seed: u64,
+    /// Models which fail the run if they regress. Every model if empty.
+    pub fail_on: Vec<String>,
+    /// Globs of models to solve, e.g. "FA0*". Every model if empty.
+    pub only: Vec<String>,
+    /// Globs of strategies to run, e.g. "Many(2)". Every strategy if empty.
+    pub strategies: Vec<String>,
+    /// Solves again even if the result is cached.
+    pub no_cache: bool,
+    /// Appends a JSON line for every task to this file.
+    pub telemetry: Option<PathBuf>,
+    /// Listens on this address, e.g. "0.0.0.0:7878", and solves on the
+    /// workers which connect to it instead of locally.
+    pub listen: Option<String>,
+}
+
+// Shared by every model in a ci run.
+struct CiContext<'a> {
+    strategies: &'a [Strategy],
+    ledger: Mutex<Ledger>,
+    scores: &'a ScoreTable,
+    cache: Option<Cache>,
+    coordinator: Option<Coordinator>,
+    progress: Progress,
+}
 
-    fn is_best(&self, problem_name: &str, energy: i64) -> bool {
-        if let Some(best_score) = self.best_scores.get(problem_name) {
-            energy < best_score.energy
-        } else {
-            true
+// Splits `strategies` into the cached results and the strategies to solve. A
+// cache entry which can not be read is solved again.
+fn ci_cached(
+    files: &ProblemFiles,
+    sha256: &str,
+    cache: Option<&Cache>,
+    strategies: &[Strategy],
+) -> (Vec<RunResult>, Vec<Strategy>) {
+    let mut cached = vec![];
+    let mut uncached = vec![];
+    for strategy in strategies {
+        let run_result = cache.and_then(|c| match c.get(&files.name, sha256, *strategy) {
+            Ok(run_result) => run_result,
+            Err(e) => {
+                warn!(
+                    "Broken cache entry: model: {}, strategy: {}: {}",
+                    files.name, strategy, e
+                );
+                None
+            }
+        });
+        match run_result {
+            Some(run_result) => cached.push(run_result),
+            None => uncached.push(*strategy),
         }
     }
+    (cached, uncached)
+}
 
-    fn write_best_trace_if(&mut self, run_result: &RunResult) -> Result<()> {
-        assert!(run_result.energy.is_some());
-        let energy = run_result.energy.unwrap();
-        if self.is_best(&run_result.model_name(), energy) {
-            info!(
-                "Found the best score: model: {}, ai: {:?}, energy: {}",
-                run_result.model_name(),
-                run_result.ai,
-                energy
-            );
-            self.best_scores.insert(
-                run_result.model_name().to_string(),
-                BestScore {
-                    ai: format!("{:?}", run_result.ai),
-                    energy,
-                },
-            );
-            run_result
-                .trace()
-                .write_to_submit_dir(&run_result.model_name())?;
-            self.write()?;
+fn ci_run_bots(files: &ProblemFiles, ctx: &CiContext, deadline: &Deadline) -> Option<ModelReport> {
+    let strategies = ctx.strategies;
+    let progress = &ctx.progress;
+    let cache = ctx.cache.as_ref();
+    if deadline.is_expired() {
+        info!("Skipped: model: {}: time budget expired", files.name);
+        progress.skip(strategies.len());
+        return None;
+    }
+    let problem = files.read().unwrap();
+    let sha256 = files.sha256().unwrap();
+    let (mut cached, uncached) = ci_cached(files, &sha256, cache, strategies);
+    info!(
+        "model: {}, cached: {}, to solve: {}",
+        files.name,
+        cached.len(),
+        uncached.len()
+    );
+    progress.skip(cached.len());
+    let mut portfolio = match ctx.coordinator.as_ref() {
+        Some(coordinator) => {
+            coordinator.solve_portfolio(files, &problem, &uncached, deadline, progress, cache)
+        }
+        None => {
+            solve_portfolio_with_progress(&problem, &uncached, deadline, progress, cache, &sha256)
         }
-        Ok(())
     }
+    .unwrap();
+    if portfolio.best().is_none() && cached.iter().all(|r| r.energy.is_none()) {
+        // The baseline never fails, and is better than no trace at all.
+        info!("Falling back to Default: model: {}", problem.name);
+        progress.add_tasks(1);
+        let (fallback_cached, fallback) = ci_cached(files, &sha256, cache, &[Ai::Default.into()]);
+        progress.skip(fallback_cached.len());
+        cached.extend(fallback_cached);
+        let fallback =
+            solve_portfolio_with_progress(&problem, &fallback, deadline, progress, cache, &sha256)
+                .unwrap();
+        portfolio.results.extend(fallback.results);
+    }
+    for run_result in &portfolio.results {
+        run_result.write_trace().unwrap();
+    }
+    portfolio.results.extend(cached);
+    let best = portfolio.best();
+    // Simulated before taking the lock, which only guards the compare and the
+    // write.
+    let verified = best.map(|run_result| (run_result, Ledger::verify(&problem, run_result)));
+    let best_before = {
+        let mut ledger = ctx.ledger.lock().unwrap();
+        let best_before = ledger.best(&problem.name).cloned();
+        let accepted = match verified {
+            Some((run_result, Ok(result))) => ledger.accept(&problem, run_result, &result),
+            Some((_, Err(e))) => Err(e),
+            None => Ok(false),
+        };
+        if let Err(e) = accepted {
+            warn!("Not accepted: model: {}: {}", problem.name, e);
+        }
+        best_before
+    };
+    let best_now = best.and_then(|r| r.energy);
+    let rerun = reran_best(best_before.as_ref(), &portfolio.results);
+    let best_before = best_before.map(|entry| entry.energy);
+    let best_ever = best_before.into_iter().chain(best_now).min();
+    Some(ModelReport {
+        model_name: problem.name.clone(),
+        r: problem.r(),
+        best_before,
+        rerun,
+        best_now,
+        strategy: best.map(|r| r.strategy.to_string()),
+        failures: portfolio
+            .results
+            .iter()
+            .filter(|r| r.energy.is_none())
+            .map(|r| r.strategy.to_string())
+            .collect(),
+        lower_bound: problem.energy_lower_bound(),
+        score: best_ever.and_then(|e| {
+            ctx.scores.default_energy(files, deadline)?;
+            ctx.scores.score(&problem, e)
+        }),
+    })
 }
 
-fn ci_run_bots(model_id: ModelId, ais: &[Ai], submit: &Arc<Mutex<Submit>>) {
-    ais.par_iter().for_each(|ai| {
-        if let Ok(run_result) = solve(&Model::read_contest_model(model_id).unwrap(), *ai) {
-            run_result.write_trace().unwrap();
-            if run_result.energy.is_some() {
-                let mut submit = submit.lock().unwrap();
-                submit.write_best_trace_if(&run_result).unwrap();
-            }
-        }
-    });
+// Whether `results` include the strategy of the best entry before, so that
+// they can tell a regression. A run restricted by `--strategy` or `--seeds`
+// may not.
+fn reran_best(best_before: Option<&LedgerEntry>, results: &[RunResult]) -> bool {
+    best_before.is_none_or(|entry| results.iter().any(|r| entry.is_from(r.strategy)))
 }
 
-pub fn ci() -> Result<()> {
-    let mut model_id_list = vec![];
-    for i in 1..=180 {
-        model_id_list.push(ModelId::Assemble(Some(i)));
-        model_id_list.push(ModelId::Disassemble(Some(i)));
-    }
+fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns.is_empty() || patterns.iter().any(|p| glob_match(p, name))
+}
+
+// Orders problems by the points which could still be gained, so that the
+// most valuable ones are solved first when the time budget is limited.
+fn prioritize<'a>(
+    problems: &'a [ProblemFiles],
+    ledger: &Ledger,
+    scores: &ScoreTable,
+) -> Result<Vec<&'a ProblemFiles>> {
+    let mut potentials = problems
+        .par_iter()
+        .map(|files| {
+            let problem = files.read()?;
+            let energy = ledger.best(&files.name).map(|e| e.energy);
+            Ok((scores.potential(&problem, energy), files))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    potentials.sort_by_key(|&(potential, _)| Reverse(potential));
+    Ok(potentials.into_iter().map(|(_, files)| files).collect())
+}
+
+/// Solves every problem and writes a report which compares them with the
+/// ledger. Fails if any model in `config.fail_on` regresses.
+pub fn ci(config: &CiConfig) -> Result<()> {
+    let problems = discover_problems(contest::problems_dir())?;
+    info!(
+        "ci: {} problems in {}",
+        problems.len(),
+        contest::problems_dir().display()
+    );
+    let problems = problems
+        .into_iter()
+        .filter(|files| matches_any(&config.only, &files.name))
+        .collect::<Vec<_>>();
     let ais = vec![2, 3, 4, 6, 8, 12, 20]
         .into_iter()
         .map(Ai::Many)
+        .filter(|ai| matches_any(&config.strategies, &format!("{:?}", ai)))
         .collect::<Vec<_>>();
+    let strategies = Strategy::with_seeds(&ais, config.seed, config.seeds);
+    let cache = if config.no_cache {
+        None
+    } else {
+        Some(Cache::new(contest::cache_dir()))
+    };
 
-    let submit = Arc::new(Mutex::new(Submit::read()?));
-    model_id_list
-        .par_iter()
-        .for_each(|model_id| ci_run_bots(*model_id, &ais, &submit));
+    let deadline = Deadline::after(config.timeout);
+    let scores = ScoreTable::read()?;
+    let ledger = Ledger::read()?;
+    let problems = prioritize(&problems, &ledger, &scores)?;
+    let progress = Progress::new(
+        problems.len() * strategies.len(),
+        config.telemetry.as_deref(),
+    )?;
+    let coordinator = match config.listen.as_ref() {
+        Some(addr) => {
+            let coordinator = Coordinator::bind(addr)?;
+            eprintln!("Waiting for workers on {}", coordinator.local_addr());
+            Some(coordinator)
+        }
+        None => None,
+    };
+    let ctx = CiContext {
+        strategies: &strategies,
+        ledger: Mutex::new(ledger),
+        scores: &scores,
+        cache,
+        coordinator,
+        progress,
+    };
+    // `par_bridge` takes problems in order, unlike `par_iter`.
+    let models = problems
+        .into_iter()
+        .par_bridge()
+        .filter_map(|files| ci_run_bots(files, &ctx, &deadline.within(config.model_timeout)))
+        .collect::<Vec<_>>();
+    // Workers stop now, not after the report.
+    drop(ctx);
+    let report = CiReport::new(models);
+    print!("{}", report.to_markdown());
+    report.write()?;
+
+    let regressions = report.regressions(&config.fail_on);
+    if !regressions.is_empty() {
+        let names = regressions
+            .iter()
+            .map(|m| m.model_name.as_str())
+            .collect::<Vec<_>>();
+        return Err(Regression(names.join(", ")).into());
+    }
     Ok(())
 }
 
@@ -200,10 +530,11 @@ mod test {
     fn assemble_test() {
         let expected_energy = [(1, 955, 11522830)];
         for (id, cmds, energy) in &expected_energy {
-            let model = Model::read_contest_model(ModelId::Assemble(Some(*id as usize))).unwrap();
-            let run_result = solve(&model, Ai::Many(2)).unwrap();
+            let model =
+                Model::read_contest_model(ModelId::Assemble(Some(format!("FA{:03}", id)))).unwrap();
+            let run_result = solve(&model.into(), Ai::Many(2).into(), &Deadline::none()).unwrap();
             assert_eq!(run_result.energy.unwrap(), *energy);
-            assert_eq!(run_result.trace().cmds.len(), *cmds);
+            assert_eq!(run_result.trace.cmds.len(), *cmds);
         }
     }
 
@@ -212,11 +543,178 @@ mod test {
         let expected_energy = [(1, 913, 11029332)];
         for (id, cmds, energy) in &expected_energy {
             let model =
-                Model::read_contest_model(ModelId::Disassemble(Some(*id as usize))).unwrap();
-            let run_result = solve(&model, Ai::Many(2)).unwrap();
+                Model::read_contest_model(ModelId::Disassemble(Some(format!("FD{:03}", id))))
+                    .unwrap();
+            let run_result = solve(&model.into(), Ai::Many(2).into(), &Deadline::none()).unwrap();
             assert_eq!(run_result.energy.unwrap(), *energy);
-            assert_eq!(run_result.trace().cmds.len(), *cmds);
+            assert_eq!(run_result.trace.cmds.len(), *cmds);
         }
     }
 
+    #[test]
+    fn portfolio_test() {
+        let targets = (1..4)
+            .flat_map(|x| (0..3).map(move |y| Cord::new(x, y, 2)))
+            .collect();
+        let problem = Model::from_cords(ModelId::Assemble(None), 6, targets).into();
+        let ais = Strategy::with_seeds(&[Ai::Many(1), Ai::Many(2), Ai::Many(3)], 0, 0);
+        let portfolio = solve_portfolio(&problem, &ais, &Deadline::none()).unwrap();
+        assert_eq!(portfolio.results.len(), 3);
+        let best = portfolio.best().unwrap();
+        for run_result in &portfolio.results {
+            let energy = simulator::verify(&problem, &run_result.trace)
+                .unwrap()
+                .energy;
+            assert_eq!(run_result.energy, Some(energy));
+            assert!(best.energy.unwrap() <= energy);
+        }
+
+        // Every strategy times out, and none is relabelled.
+        let expired = Deadline::after(Some(Duration::from_secs(0)));
+        let portfolio = solve_portfolio(&problem, &ais, &expired).unwrap();
+        for (run_result, strategy) in portfolio.results.iter().zip(&ais) {
+            assert_eq!(run_result.energy, None);
+            assert_eq!(run_result.strategy, *strategy);
+        }
+        let expired_default = solve(&problem, Ai::Default.into(), &expired).unwrap();
+        assert_eq!(expired_default.energy, None);
+    }
+
+    #[test]
+    fn reassemble_test() {
+        let src = (1..4).map(|y| Cord::new(2, y - 1, 2)).collect();
+        let tgt = (1..4).map(|x| Cord::new(x, 0, 3)).collect();
+        let problem = Problem::new(
+            "FR000".to_string(),
+            Some(Model::from_cords(ModelId::Disassemble(None), 6, src)),
+            Some(Model::from_cords(ModelId::Assemble(None), 6, tgt)),
+        );
+        let run_result = solve(&problem, Ai::Many(2).into(), &Deadline::none()).unwrap();
+        let energy = simulator::verify(&problem, &run_result.trace)
+            .unwrap()
+            .energy;
+        assert_eq!(run_result.energy, Some(energy));
+    }
+
+    #[test]
+    fn default_test() {
+        let src = (1..4)
+            .flat_map(|x| (0..3).map(move |y| Cord::new(x, y, 2)))
+            .collect();
+        let tgt = (1..4)
+            .flat_map(|y| (0..20).map(move |z| Cord::new(y, 0, z)))
+            .filter(|c| c.z % 3 != 1)
+            .collect();
+        let problem = Problem::new(
+            "FR000".to_string(),
+            Some(Model::from_cords(ModelId::Disassemble(None), 20, src)),
+            Some(Model::from_cords(ModelId::Assemble(None), 20, tgt)),
+        );
+        let run_result = solve(&problem, Ai::Default.into(), &Deadline::none()).unwrap();
+        let result = simulator::verify(&problem, &run_result.trace).unwrap();
+        assert_eq!(run_result.energy, Some(result.energy));
+        assert_eq!(run_result.strategy.to_string(), "Default");
+    }
+
+    #[test]
+    fn seed_test() {
+        let targets = (0..4)
+            .flat_map(|x| (0..3).flat_map(move |y| (1..4).map(move |z| Cord::new(x, y, z))))
+            .collect();
+        let problem = Model::from_cords(ModelId::Assemble(None), 8, targets).into();
+        let solve_with = |seed| {
+            let strategy = Strategy {
+                ai: Ai::Many(4),
+                seed,
+            };
+            solve(&problem, strategy, &Deadline::none())
+                .unwrap()
+                .trace
+                .cmds
+        };
+        assert_eq!(solve_with(Some(1)), solve_with(Some(1)));
+        assert!((2..10).any(|seed| solve_with(Some(seed)) != solve_with(Some(1))));
+
+        let strategies = Strategy::with_seeds(&[Ai::Many(2), Ai::Many(4)], 10, 3);
+        assert_eq!(strategies.len(), 6);
+        assert_eq!(strategies[5].seed, Some(12));
+        assert_eq!(strategies[5].to_string(), "Many(4)-seed12");
+    }
+
+    #[test]
+    fn strategy_filter_test() {
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let problem = Model::from_cords(ModelId::Assemble(None), 6, targets).into();
+        let before: LedgerEntry =
+            serde_json::from_str(r#"{"ai": "Many(2)", "energy": 1}"#).unwrap();
+        let report = |patterns: &[&str]| {
+            let patterns = patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+            let ais = [Ai::Many(2), Ai::Many(3)]
+                .iter()
+                .cloned()
+                .filter(|ai| matches_any(&patterns, &format!("{:?}", ai)))
+                .collect::<Vec<_>>();
+            let strategies = Strategy::with_seeds(&ais, 0, 0);
+            let portfolio = solve_portfolio(&problem, &strategies, &Deadline::none()).unwrap();
+            CiReport::new(vec![ModelReport {
+                model_name: problem.name.clone(),
+                r: problem.r(),
+                best_before: Some(before.energy),
+                rerun: reran_best(Some(&before), &portfolio.results),
+                best_now: portfolio.best().and_then(|r| r.energy),
+                strategy: None,
+                failures: vec![],
+                lower_bound: problem.energy_lower_bound(),
+                score: None,
+            }])
+        };
+
+        // `--strategy "Many(3)"` does not run the strategy of the best before.
+        let filtered = report(&["Many(3)"]);
+        assert_eq!(filtered.models[0].status(), Status::Partial);
+        assert!(filtered.regressions(&[]).is_empty());
+
+        let full = report(&[]);
+        assert_eq!(full.models[0].status(), Status::Regressed);
+        assert_eq!(full.regressions(&[]).len(), 1);
+    }
+
+    #[test]
+    fn ci_cached_test() {
+        let dir = std::env::temp_dir().join(format!("icfp2018-ci-cached-{}", std::process::id()));
+        let model_dir = dir.join("model");
+        std::fs::create_dir_all(&model_dir).unwrap();
+        let targets = (1..4).map(|x| Cord::new(x, 0, 2)).collect();
+        let model = Model::from_cords(ModelId::Assemble(None), 6, targets);
+        std::fs::write(model_dir.join("FA001_tgt.mdl"), model.encode()).unwrap();
+        let files = &discover_problems(&model_dir).unwrap()[0];
+        let sha256 = files.sha256().unwrap();
+
+        let cache = Cache::new(dir.join("cache"));
+        let strategies = [Ai::Many(2).into(), Ai::Default.into()];
+        let run_result = solve(&files.read().unwrap(), strategies[1], &Deadline::none()).unwrap();
+        cache.put(&sha256, &run_result).unwrap();
+        let (cached, uncached) = ci_cached(files, &sha256, Some(&cache), &strategies);
+        assert_eq!(cached[0].strategy, strategies[1]);
+        assert_eq!(uncached, vec![strategies[0]]);
+
+        // A broken entry is a miss.
+        for entry in std::fs::read_dir(dir.join("cache").join("FA001")).unwrap() {
+            std::fs::write(entry.unwrap().path(), b"{").unwrap();
+        }
+        let (cached, uncached) = ci_cached(files, &sha256, Some(&cache), &strategies);
+        assert!(cached.is_empty());
+        assert_eq!(uncached.len(), 2);
+
+        // A result which timed out is not cached.
+        let expired = Deadline::after(Some(Duration::from_secs(0)));
+        let run_result = solve(&files.read().unwrap(), strategies[0], &expired).unwrap();
+        assert!(run_result.timed_out);
+        cache_result(Some(&cache), &sha256, &run_result);
+        assert!(cache
+            .get("FA001", &sha256, strategies[0])
+            .unwrap()
+            .is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,224 @@
+// Contest scores.
+//
+// The score of a problem is
+//
+//   ⌈log2 R⌉ * 1000 * (E_default - E_team) / (E_default - E_best)
+//
+// where E_default is the energy of the default trace, and E_best is the best
+// energy among all teams. E_best is read from `score/best.json` in the
+// contest directory if it is known, and is the lower bound otherwise, so the
+// expected score is a pessimistic one.
+
+use serde_json;
+
+use std;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use super::ai::*;
+use super::contest;
+use super::deadline::*;
+use super::ledger::*;
+use super::prelude::*;
+use super::problem::*;
+use super::run::solve;
+use super::simulator;
+use super::trace::*;
+use rayon::prelude::*;
+
+pub fn weight(r: usize) -> i64 {
+    let mut w = 0;
+    while (1 << w) < r {
+        w += 1;
+    }
+    w
+}
+
+pub fn max_score(r: usize) -> i64 {
+    weight(r) * 1000
+}
+
+pub fn score(r: usize, default_energy: i64, energy: i64, best_energy: i64) -> i64 {
+    let best_energy = best_energy.min(energy);
+    if energy >= default_energy {
+        return 0;
+    }
+    max_score(r) * (default_energy - energy) / (default_energy - best_energy)
+}
+
+// Energies of the default traces are cached in `trace/sweep/energy.json`,
+// since simulating them takes a while.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedEnergy {
+    sha256: String,
+    energy: i64,
+}
+
+// The default trace of a problem: the one given by the organizers in
+// `trace/default/`, or the one generated with `Ai::Default` in `trace/sweep/`.
+fn default_trace(files: &ProblemFiles, deadline: &Deadline) -> Result<Vec<u8>> {
+    let path = contest::default_trace_dir().join(format!("{}.nbt", files.name));
+    if path.exists() {
+        return Ok(std::fs::read(&path)?);
+    }
+    let dir = contest::sweep_trace_dir();
+    let path = dir.join(format!("{}.nbt", files.name));
+    if !path.exists() {
+        info!("Generating the default trace: {}", path.display());
+        let run_result = solve(&files.read()?, Ai::Default.into(), deadline)?;
+        if run_result.energy.is_none() {
+            return Err(NanoBotError.into());
+        }
+        std::fs::create_dir_all(&dir)?;
+        run_result.trace.write_to(&path)?;
+    }
+    Ok(std::fs::read(&path)?)
+}
+
+pub struct ScoreTable {
+    // Energies of the default traces, by problem. Filled as they are needed.
+    default_energies: Mutex<BTreeMap<String, CachedEnergy>>,
+    /// The best energies among all teams, if known.
+    pub best_energies: HashMap<String, i64>,
+}
+
+impl ScoreTable {
+    pub fn read() -> Result<ScoreTable> {
+        let path = contest::contest_dir().join("score").join("best.json");
+        let best_energies = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            Default::default()
+        };
+        let cache_path = contest::sweep_trace_dir().join("energy.json");
+        let default_energies = if cache_path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&cache_path)?)?
+        } else {
+            Default::default()
+        };
+        Ok(ScoreTable {
+            default_energies: Mutex::new(default_energies),
+            best_energies,
+        })
+    }
+
+    /// The energy of the default trace of `files`, which is generated and
+    /// simulated if it is not known yet. None if it fails or `deadline`
+    /// expires, in which case the problem has no score.
+    pub fn default_energy(&self, files: &ProblemFiles, deadline: &Deadline) -> Option<i64> {
+        let result = default_trace(files, deadline).and_then(|bytes| {
+            let sha256 = sha256_hex(&bytes);
+            if let Some(cached) = self.default_energies.lock().unwrap().get(&files.name) {
+                if cached.sha256 == sha256 {
+                    return Ok(cached.energy);
+                }
+            }
+            info!("Simulating the default trace: model: {}", files.name);
+            let energy = simulator::verify(&files.read()?, &Trace::decode(&bytes)?)?.energy;
+            let mut energies = self.default_energies.lock().unwrap();
+            energies.insert(files.name.clone(), CachedEnergy { sha256, energy });
+            let dir = contest::sweep_trace_dir();
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(
+                dir.join("energy.json"),
+                serde_json::to_string_pretty(&*energies)?,
+            )?;
+            Ok(energy)
+        });
+        match result {
+            Ok(energy) => Some(energy),
+            Err(e) => {
+                warn!("No default energy: model: {}: {}", files.name, e);
+                None
+            }
+        }
+    }
+
+    fn known_default_energy(&self, name: &str) -> Option<i64> {
+        self.default_energies
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|cached| cached.energy)
+    }
+
+    fn best_energy(&self, problem: &Problem) -> i64 {
+        match self.best_energies.get(&problem.name) {
+            Some(energy) => *energy,
+            None => problem.energy_lower_bound(),
+        }
+    }
+
+    /// The expected score of `energy`, or None if the default energy of the
+    /// problem is not known yet.
+    pub fn score(&self, problem: &Problem, energy: i64) -> Option<i64> {
+        let default_energy = self.known_default_energy(&problem.name)?;
+        Some(score(
+            problem.r(),
+            default_energy,
+            energy,
+            self.best_energy(problem),
+        ))
+    }
+
+    /// Points which could still be gained for `problem`.
+    pub fn potential(&self, problem: &Problem, energy: Option<i64>) -> i64 {
+        let score = match energy {
+            Some(energy) => self.score(problem, energy).unwrap_or(0),
+            None => 0,
+        };
+        max_score(problem.r()) - score
+    }
+}
+
+/// Prints the expected score of every problem in the ledger.
+pub fn print_scores() -> Result<()> {
+    let problems = discover_problems(contest::problems_dir())?;
+    let table = ScoreTable::read()?;
+    let default_energies = problems
+        .par_iter()
+        .map(|files| table.default_energy(files, &Deadline::none()))
+        .collect::<Vec<_>>();
+    let ledger = Ledger::read()?;
+    let mut total = 0;
+    let mut total_max = 0;
+    for (files, default_energy) in problems.iter().zip(default_energies) {
+        let problem = files.read()?;
+        let energy = ledger.best(&files.name).map(|e| e.energy);
+        let score = energy.and_then(|e| table.score(&problem, e));
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            problem.name,
+            problem.r(),
+            default_energy.map_or("-".to_string(), |e| e.to_string()),
+            energy.map_or("-".to_string(), |e| e.to_string()),
+            score.map_or("-".to_string(), |s| s.to_string()),
+            max_score(problem.r())
+        );
+        total += score.unwrap_or(0);
+        total_max += max_score(problem.r());
+    }
+    println!("total\t{}\t{}", total, total_max);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn score_test() {
+        assert_eq!(weight(1), 0);
+        assert_eq!(weight(2), 1);
+        assert_eq!(weight(20), 5);
+        assert_eq!(weight(32), 5);
+        assert_eq!(weight(250), 8);
+
+        assert_eq!(score(20, 1000, 1000, 100), 0);
+        assert_eq!(score(20, 1000, 2000, 100), 0);
+        assert_eq!(score(20, 1000, 100, 100), 5000);
+        assert_eq!(score(20, 1000, 550, 100), 2500);
+        // Better than the best known energy.
+        assert_eq!(score(20, 1000, 50, 100), 5000);
+    }
+}
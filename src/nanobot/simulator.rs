@@ -0,0 +1,745 @@
+// Standalone simulator.
+//
+// `System` executes commands produced by our own AI and trusts them. This one
+// executes any trace, checks every rule of the specification and reports the
+// first violation, so that it can be used to verify traces independently.
+
+use serde_json;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use super::bot::*;
+use super::matrix::*;
+use super::model::*;
+use super::prelude::*;
+use super::problem::*;
+use super::system::Harmonics;
+use super::trace::*;
+
+#[derive(Fail, Debug)]
+#[fail(display = "Invalid trace: time step: {}, {}", time_step, reason)]
+pub struct InvalidTrace {
+    pub time_step: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulateResult {
+    pub energy: i64,
+    pub time_steps: usize,
+    pub cmds: usize,
+}
+
+pub struct Simulator {
+    pub r: usize,
+    pub energy: i64,
+    pub harmonics: Harmonics,
+    pub matrix: Matrix,
+    pub bots: Vec<Bot>,
+    pub bot_index: usize,
+    pub time_step: usize,
+    pub cmds: usize,
+    pub halted: bool,
+    volatile: HashSet<Cord>,
+    // Added by the current command.
+    volatile_added: Vec<Cord>,
+    new_bots: Vec<Bot>,
+    fusion_primaries: Vec<(Cord, Cord)>,   // (primary, secondary)
+    fusion_secondaries: Vec<(Cord, Cord)>, // (primary, secondary)
+    flip: bool,
+    // Every voxel filled or voided so far, in order. The ones from
+    // `checked_*` are not checked to be grounded yet, and the ones from
+    // `step_*` are of the current time step.
+    filled: Vec<Cord>,
+    voided: Vec<Cord>,
+    checked_filled: usize,
+    checked_voided: usize,
+    step_filled: usize,
+    step_voided: usize,
+}
+
+// The bots before a command. The whole state of bots is kept only for a
+// command which ends a time step.
+enum SavedBots {
+    One {
+        bot: Bot,
+        new_bots: usize,
+        fusion_primaries: usize,
+        fusion_secondaries: usize,
+    },
+    All {
+        bots: Vec<Bot>,
+        new_bots: Vec<Bot>,
+        fusion_primaries: Vec<(Cord, Cord)>,
+        fusion_secondaries: Vec<(Cord, Cord)>,
+    },
+}
+
+/// What `Simulator::step` needs to undo a command.
+pub struct Delta {
+    energy: i64,
+    harmonics: Harmonics,
+    bot_index: usize,
+    time_step: usize,
+    cmds: usize,
+    halted: bool,
+    flip: bool,
+    bots: SavedBots,
+    // The whole set if the command begins a time step.
+    volatile: Option<HashSet<Cord>>,
+    volatile_added: Vec<Cord>,
+    filled: usize,
+    voided: usize,
+    checked_filled: usize,
+    checked_voided: usize,
+    step_filled: usize,
+    step_voided: usize,
+}
+
+impl Simulator {
+    pub fn new(matrix: Matrix) -> Simulator {
+        Simulator {
+            r: matrix.r,
+            energy: 0,
+            harmonics: Harmonics::Low,
+            matrix,
+            bots: vec![Bot::new_at_origin()],
+            bot_index: 0,
+            time_step: 0,
+            cmds: 0,
+            halted: false,
+            volatile: HashSet::new(),
+            volatile_added: vec![],
+            new_bots: vec![],
+            fusion_primaries: vec![],
+            fusion_secondaries: vec![],
+            flip: false,
+            filled: vec![],
+            voided: vec![],
+            checked_filled: 0,
+            checked_voided: 0,
+            step_filled: 0,
+            step_voided: 0,
+        }
+    }
+
+    pub fn result(&self) -> SimulateResult {
+        SimulateResult {
+            energy: self.energy,
+            time_steps: self.time_step,
+            cmds: self.cmds,
+        }
+    }
+
+    /// Volatile in the current time step, or in the last one if it is
+    /// complete.
+    pub fn is_volatile(&self, c: Cord) -> bool {
+        self.volatile.contains(&c)
+    }
+
+    /// Voxels filled in the current time step, or in the last one if it is
+    /// complete.
+    pub fn step_filled(&self) -> &[Cord] {
+        &self.filled[self.step_filled..]
+    }
+
+    pub fn step_voided(&self) -> &[Cord] {
+        &self.voided[self.step_voided..]
+    }
+
+    fn invalid(&self, reason: impl Into<String>) -> InvalidTrace {
+        InvalidTrace {
+            time_step: self.time_step,
+            reason: reason.into(),
+        }
+    }
+
+    fn mark_volatile(&mut self, c: Cord) -> Result<()> {
+        if !c.is_in_range(self.r) {
+            return Err(self.invalid(format!("out of range: {:?}", c)).into());
+        }
+        if !self.volatile.insert(c) {
+            return Err(self.invalid(format!("interfered: {:?}", c)).into());
+        }
+        self.volatile_added.push(c);
+        Ok(())
+    }
+
+    fn mark_move(&mut self, from: Cord, diff: CordDiff) -> Result<Cord> {
+        let direc = diff.direc();
+        let mut c = from;
+        for _ in 0..diff.mlen() {
+            c = c + direc;
+            self.mark_volatile(c)?;
+            if self.matrix[c] {
+                return Err(self
+                    .invalid(format!("move into full voxel: {:?}", c))
+                    .into());
+            }
+        }
+        Ok(c)
+    }
+
+    fn begin_time_step(&mut self) {
+        let r = self.r as i64;
+        self.energy += match self.harmonics {
+            Harmonics::High => 30 * r * r * r,
+            Harmonics::Low => 3 * r * r * r,
+        };
+        self.energy += 20 * self.bots.len() as i64;
+        self.volatile = self.bots.iter().map(|b| b.pos).collect();
+        self.step_filled = self.filled.len();
+        self.step_voided = self.voided.len();
+    }
+
+    /// Executes the command of the current bot. The time step completes when
+    /// every bot has got its command.
+    pub fn execute(&mut self, cmd: Cmd) -> Result<()> {
+        use self::Cmd::*;
+
+        self.volatile_added.clear();
+        if self.halted {
+            return Err(self.invalid("command after Halt").into());
+        }
+        if self.bot_index == 0 {
+            self.begin_time_step();
+        }
+        let pos = self.bots[self.bot_index].pos;
+        match cmd {
+            Halt => {
+                if !pos.is_origin() || self.bots.len() != 1 || self.harmonics != Harmonics::Low {
+                    return Err(self.invalid("Halt in a wrong state").into());
+                }
+                self.halted = true;
+            }
+            Wait => {}
+            Flip => {
+                self.flip = !self.flip;
+            }
+            SMove(lld) => {
+                let c = self.mark_move(pos, lld.0)?;
+                self.bots[self.bot_index].pos = c;
+                self.energy += 2 * lld.0.mlen() as i64;
+            }
+            LMove(sld1, sld2) => {
+                let c = self.mark_move(pos, sld1.0)?;
+                let c = self.mark_move(c, sld2.0)?;
+                self.bots[self.bot_index].pos = c;
+                self.energy += 2 * (sld1.0.mlen() + 2 + sld2.0.mlen()) as i64;
+            }
+            Fission(nd, m) => {
+                let c = pos + nd.0;
+                self.mark_volatile(c)?;
+                if self.matrix[c] {
+                    return Err(self
+                        .invalid(format!("Fission into full voxel: {:?}", c))
+                        .into());
+                }
+                if m >= self.bots[self.bot_index].seeds.len() {
+                    return Err(self.invalid("Fission without enough seeds").into());
+                }
+                let new_bot = self.bots[self.bot_index].fission(nd, m);
+                self.new_bots.push(new_bot);
+                self.energy += 24;
+            }
+            Fill(nd) => {
+                let c = pos + nd.0;
+                self.mark_volatile(c)?;
+                if self.matrix[c] {
+                    self.energy += 6;
+                } else {
+                    self.matrix.fill(c);
+                    self.filled.push(c);
+                    self.energy += 12;
+                }
+            }
+            Void(nd) => {
+                let c = pos + nd.0;
+                self.mark_volatile(c)?;
+                if self.matrix[c] {
+                    self.matrix.void(c);
+                    self.voided.push(c);
+                    self.energy -= 12;
+                } else {
+                    self.energy += 3;
+                }
+            }
+            FusionP(nd) => {
+                self.fusion_primaries.push((pos, pos + nd.0));
+            }
+            FusionS(nd) => {
+                self.fusion_secondaries.push((pos + nd.0, pos));
+            }
+        }
+        self.cmds += 1;
+        self.bot_index += 1;
+        if self.bot_index == self.bots.len() {
+            self.end_time_step()?;
+        }
+        Ok(())
+    }
+
+    /// Executes the command like `execute`, and returns what undoes it. The
+    /// state does not change if the command fails.
+    pub fn step(&mut self, cmd: Cmd) -> Result<Delta> {
+        let ends_time_step = self.halted || self.bot_index + 1 == self.bots.len();
+        let bots = if ends_time_step {
+            SavedBots::All {
+                bots: self.bots.clone(),
+                new_bots: self.new_bots.clone(),
+                fusion_primaries: self.fusion_primaries.clone(),
+                fusion_secondaries: self.fusion_secondaries.clone(),
+            }
+        } else {
+            SavedBots::One {
+                bot: self.bots[self.bot_index].clone(),
+                new_bots: self.new_bots.len(),
+                fusion_primaries: self.fusion_primaries.len(),
+                fusion_secondaries: self.fusion_secondaries.len(),
+            }
+        };
+        let volatile = if self.bot_index == 0 && !self.halted {
+            Some(std::mem::take(&mut self.volatile))
+        } else {
+            None
+        };
+        let mut delta = Delta {
+            energy: self.energy,
+            harmonics: self.harmonics,
+            bot_index: self.bot_index,
+            time_step: self.time_step,
+            cmds: self.cmds,
+            halted: self.halted,
+            flip: self.flip,
+            bots,
+            volatile,
+            volatile_added: vec![],
+            filled: self.filled.len(),
+            voided: self.voided.len(),
+            checked_filled: self.checked_filled,
+            checked_voided: self.checked_voided,
+            step_filled: self.step_filled,
+            step_voided: self.step_voided,
+        };
+        let result = self.execute(cmd);
+        delta.volatile_added = std::mem::take(&mut self.volatile_added);
+        match result {
+            Ok(()) => Ok(delta),
+            Err(e) => {
+                self.undo(delta);
+                Err(e)
+            }
+        }
+    }
+
+    /// Undoes the last command executed by `step`.
+    pub fn undo(&mut self, delta: Delta) {
+        for c in self.filled.drain(delta.filled..) {
+            self.matrix.void(c);
+        }
+        for c in self.voided.drain(delta.voided..) {
+            self.matrix.fill(c);
+        }
+        match delta.volatile {
+            Some(volatile) => self.volatile = volatile,
+            None => {
+                for c in &delta.volatile_added {
+                    self.volatile.remove(c);
+                }
+            }
+        }
+        match delta.bots {
+            SavedBots::One {
+                bot,
+                new_bots,
+                fusion_primaries,
+                fusion_secondaries,
+            } => {
+                self.bots[delta.bot_index] = bot;
+                self.new_bots.truncate(new_bots);
+                self.fusion_primaries.truncate(fusion_primaries);
+                self.fusion_secondaries.truncate(fusion_secondaries);
+            }
+            SavedBots::All {
+                bots,
+                new_bots,
+                fusion_primaries,
+                fusion_secondaries,
+            } => {
+                self.bots = bots;
+                self.new_bots = new_bots;
+                self.fusion_primaries = fusion_primaries;
+                self.fusion_secondaries = fusion_secondaries;
+            }
+        }
+        self.energy = delta.energy;
+        self.harmonics = delta.harmonics;
+        self.bot_index = delta.bot_index;
+        self.time_step = delta.time_step;
+        self.cmds = delta.cmds;
+        self.halted = delta.halted;
+        self.flip = delta.flip;
+        self.checked_filled = delta.checked_filled;
+        self.checked_voided = delta.checked_voided;
+        self.step_filled = delta.step_filled;
+        self.step_voided = delta.step_voided;
+    }
+
+    fn end_time_step(&mut self) -> Result<()> {
+        self.fusion_primaries.sort();
+        self.fusion_secondaries.sort();
+        if self.fusion_primaries != self.fusion_secondaries {
+            return Err(self.invalid("unpaired Fusion").into());
+        }
+        for (p, s) in std::mem::take(&mut self.fusion_primaries) {
+            let secondary = match self.bots.iter().position(|b| b.pos == s) {
+                Some(i) => self.bots.remove(i),
+                None => return Err(self.invalid(format!("no bot to fuse at {:?}", s)).into()),
+            };
+            let primary = self.bots.iter_mut().find(|b| b.pos == p).unwrap();
+            primary.fusion(&secondary);
+            self.energy -= 24;
+        }
+        self.fusion_secondaries.clear();
+
+        self.bots.append(&mut self.new_bots);
+        self.bots.sort_by_key(|b| b.bid);
+        if self.flip {
+            self.harmonics = match self.harmonics {
+                Harmonics::High => Harmonics::Low,
+                Harmonics::Low => Harmonics::High,
+            };
+            self.flip = false;
+        }
+        if self.harmonics == Harmonics::Low {
+            if !self.is_grounded_after_changes() {
+                return Err(self.invalid("ungrounded voxel in Low harmonics").into());
+            }
+            self.checked_filled = self.filled.len();
+            self.checked_voided = self.voided.len();
+        }
+        if self.halted {
+            self.bots.clear();
+        }
+        self.bot_index = 0;
+        self.time_step += 1;
+        Ok(())
+    }
+
+    // Every full voxel was grounded when this was checked last time, so only
+    // the voxels filled since then, and the neighbours of the voxels voided
+    // since then, can be ungrounded now.
+    fn is_grounded_after_changes(&self) -> bool {
+        let filled = &self.filled[self.checked_filled..];
+        let voided = &self.voided[self.checked_voided..];
+
+        let mut fresh: HashSet<Cord> = filled.iter().cloned().filter(|c| self.matrix[*c]).collect();
+        let mut suspects: Vec<Cord> = fresh.iter().cloned().collect();
+        if !voided.is_empty() {
+            // Voiding can disconnect anything, so no old voxel is trusted.
+            fresh.clear();
+            for c in voided {
+                for diff in CordDiff::gen_all_diff() {
+                    let n = *c + *diff;
+                    if n.is_in_range(self.r) && self.matrix[n] {
+                        suspects.push(n);
+                    }
+                }
+            }
+        }
+
+        let mut grounded = HashSet::new();
+        suspects
+            .into_iter()
+            .all(|c| self.is_grounded(c, &fresh, voided.is_empty(), &mut grounded))
+    }
+
+    fn is_grounded(
+        &self,
+        start: Cord,
+        fresh: &HashSet<Cord>,
+        trust_old: bool,
+        grounded: &mut HashSet<Cord>,
+    ) -> bool {
+        // Depth first search which prefers going down.
+        let mut stack = vec![start];
+        let mut visited = HashSet::new();
+        visited.insert(start);
+        while let Some(c) = stack.pop() {
+            if c.y == 0 || grounded.contains(&c) || (trust_old && !fresh.contains(&c)) {
+                grounded.extend(visited);
+                return true;
+            }
+            for diff in CordDiff::gen_all_diff().iter().rev() {
+                let n = c + *diff;
+                if n.is_in_range(self.r) && self.matrix[n] && visited.insert(n) {
+                    stack.push(n);
+                }
+            }
+        }
+        false
+    }
+}
+
+/// A simulator which starts from `src`, or an empty matrix.
+pub fn start(src: Option<&Model>, tgt: Option<&Model>) -> Result<Simulator> {
+    let r = match (src, tgt) {
+        (Some(src), Some(tgt)) if src.r != tgt.r => {
+            return Err(InvalidTrace {
+                time_step: 0,
+                reason: format!("resolution mismatch: {} and {}", src.r, tgt.r),
+            }
+            .into());
+        }
+        (Some(model), _) | (None, Some(model)) => model.r,
+        (None, None) => unreachable!(),
+    };
+    Ok(Simulator::new(match src {
+        Some(src) => Matrix::from_cords(r, &src.targets),
+        None => Matrix::empty(r),
+    }))
+}
+
+// Executes `trace` and expects `tgt`, or an empty matrix, at the end.
+fn run_to_end(sim: &mut Simulator, tgt: Option<&Model>, trace: &Trace) -> Result<()> {
+    for cmd in &trace.cmds {
+        sim.execute(*cmd)?;
+    }
+    check_end(sim, tgt)
+}
+
+/// Fails unless `sim` has halted with `tgt`, or an empty matrix.
+pub fn check_end(sim: &Simulator, tgt: Option<&Model>) -> Result<()> {
+    if !sim.halted {
+        return Err(sim.invalid("trace ends without Halt").into());
+    }
+    let expected = match tgt {
+        Some(tgt) => Matrix::from_cords(sim.r, &tgt.targets),
+        None => Matrix::empty(sim.r),
+    };
+    if sim.matrix.full != expected.full {
+        return Err(sim
+            .invalid("the final matrix does not match the target")
+            .into());
+    }
+    Ok(())
+}
+
+/// Simulates `trace`, starting from `src` (or an empty matrix) and expecting
+/// `tgt` (or an empty matrix) at the end.
+pub fn simulate(src: Option<&Model>, tgt: Option<&Model>, trace: &Trace) -> Result<SimulateResult> {
+    let mut sim = start(src, tgt)?;
+    run_to_end(&mut sim, tgt, trace)?;
+    Ok(sim.result())
+}
+
+/// Simulates a trace which was produced for `problem`.
+pub fn verify(problem: &Problem, trace: &Trace) -> Result<SimulateResult> {
+    simulate(problem.src.as_ref(), problem.tgt.as_ref(), trace)
+}
+
+/// The outcome of a simulation. The numbers are the ones at the first
+/// violation if the trace is invalid.
+#[derive(Serialize, Debug)]
+pub struct SimulateReport {
+    pub energy: i64,
+    pub time_steps: usize,
+    pub cmds: usize,
+    pub valid: bool,
+    pub error: Option<String>,
+}
+
+impl SimulateReport {
+    pub fn new(problem: &Problem, trace: &Trace) -> SimulateReport {
+        let (src, tgt) = (problem.src.as_ref(), problem.tgt.as_ref());
+        let (result, verdict) = match start(src, tgt) {
+            Ok(mut sim) => {
+                let verdict = run_to_end(&mut sim, tgt, trace);
+                (sim.result(), verdict)
+            }
+            Err(e) => (Simulator::new(Matrix::empty(problem.r())).result(), Err(e)),
+        };
+        SimulateReport {
+            energy: result.energy,
+            time_steps: result.time_steps,
+            cmds: result.cmds,
+            valid: verdict.is_ok(),
+            error: verdict.err().map(|e| e.to_string()),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "energy: {}\ntime steps: {}\ncommands: {}\nverdict: {}\n",
+            self.energy,
+            self.time_steps,
+            self.cmds,
+            match self.error.as_ref() {
+                Some(e) => e.clone(),
+                None => "valid".to_string(),
+            }
+        )
+    }
+}
+
+/// Simulates the trace file against the model files, and prints the result.
+/// Fails if the trace is invalid.
+pub fn print_simulation(
+    src: Option<String>,
+    tgt: Option<String>,
+    trace: String,
+    json: bool,
+) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let report = SimulateReport::new(&problem, &Trace::read(&trace)?);
+    if json {
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        print!("{}", report.to_text());
+    }
+    if !report.valid {
+        bail!("Invalid trace: {}", trace);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pillar() -> Problem {
+        let targets = vec![Cord::new(1, 0, 1), Cord::new(1, 1, 1)]
+            .into_iter()
+            .collect();
+        Model::from_cords(ModelId::Assemble(None), 3, targets).into()
+    }
+
+    #[test]
+    fn simulate_test() {
+        use self::Cmd::*;
+
+        let problem = pillar();
+        let trace = Trace {
+            cmds: vec![
+                SMove(LongLinear(CordDiff::new(0, 0, 1))),
+                Fission(Near(CordDiff::new(0, 1, 0)), 0),
+                Fill(Near(CordDiff::new(1, 0, 0))),
+                Fill(Near(CordDiff::new(1, 0, 0))),
+                FusionP(Near(CordDiff::new(0, 1, 0))),
+                FusionS(Near(CordDiff::new(0, -1, 0))),
+                SMove(LongLinear(CordDiff::new(0, 0, -1))),
+                Halt,
+            ],
+        };
+        let result = verify(&problem, &trace).unwrap();
+        let harmonics = 6 * 3 * 27;
+        let bots = 20 * (1 + 1 + 2 + 2 + 1 + 1);
+        assert_eq!(result.energy, harmonics + bots + 2 + 24 + 12 + 12 - 24 + 2);
+        assert_eq!(result.time_steps, 6);
+        assert_eq!(result.cmds, 8);
+    }
+
+    #[test]
+    fn simulate_error_test() {
+        use self::Cmd::*;
+
+        let problem = pillar();
+        // Ungrounded voxel in Low harmonics.
+        let trace = Trace {
+            cmds: vec![
+                SMove(LongLinear(CordDiff::new(0, 1, 0))),
+                SMove(LongLinear(CordDiff::new(1, 0, 0))),
+                Fill(Near(CordDiff::new(0, 0, 1))),
+            ],
+        };
+        assert!(verify(&problem, &trace).is_err());
+
+        // Not assembled yet.
+        let trace = Trace { cmds: vec![Halt] };
+        assert!(verify(&problem, &trace).is_err());
+
+        // Moving out of the matrix.
+        let trace = Trace {
+            cmds: vec![SMove(LongLinear(CordDiff::new(-1, 0, 0)))],
+        };
+        assert!(verify(&problem, &trace).is_err());
+    }
+
+    #[test]
+    fn step_undo_test() {
+        use self::Cmd::*;
+
+        let cmds = vec![
+            Flip,
+            SMove(LongLinear(CordDiff::new(0, 1, 0))),
+            Fission(Near(CordDiff::new(1, 0, 0)), 3),
+            Fill(Near(CordDiff::new(1, 0, 1))),
+            Fill(Near(CordDiff::new(0, -1, 1))),
+            Flip,
+            Void(Near(CordDiff::new(0, 0, 1))),
+        ];
+        let mut sim = Simulator::new(Matrix::empty(3));
+        let state = |sim: &Simulator| {
+            (
+                sim.energy,
+                sim.harmonics,
+                sim.matrix.full.clone(),
+                sim.bots
+                    .iter()
+                    .map(|b| (b.pos, b.seeds.len()))
+                    .collect::<Vec<_>>(),
+                sim.time_step,
+                sim.cmds,
+            )
+        };
+        let mut states = vec![];
+        let mut deltas = vec![];
+        for cmd in cmds {
+            states.push(state(&sim));
+            deltas.push(sim.step(cmd).unwrap());
+        }
+        assert!(sim.matrix[Cord::new(1, 0, 1)]);
+        assert!(!sim.matrix[Cord::new(1, 1, 1)]);
+        assert_eq!(sim.step_voided(), &[Cord::new(1, 1, 1)]);
+
+        // A failing command changes nothing.
+        let last = state(&sim);
+        assert!(sim
+            .step(SMove(LongLinear(CordDiff::new(-1, 0, 0))))
+            .is_err());
+        assert_eq!(last, state(&sim));
+
+        while let Some(delta) = deltas.pop() {
+            sim.undo(delta);
+            assert_eq!(states.pop().unwrap(), state(&sim));
+        }
+    }
+
+    #[test]
+    fn simulate_report_test() {
+        use self::Cmd::*;
+
+        let problem = pillar();
+        let trace = Trace {
+            cmds: vec![Flip, SMove(LongLinear(CordDiff::new(-1, 0, 0))), Halt],
+        };
+        let report = SimulateReport::new(&problem, &trace);
+        assert!(!report.valid);
+        assert_eq!(report.time_steps, 1);
+        assert_eq!(report.cmds, 1);
+        // Including the time step of the violation, in High harmonics.
+        assert_eq!(report.energy, 27 * 3 + 20 + 27 * 30 + 20);
+        assert!(report.to_text().contains("verdict: Invalid trace: "));
+
+        let trace = Trace { cmds: vec![Halt] };
+        let empty = Model::from_cords(ModelId::Assemble(None), 3, HashSet::new());
+        let report = SimulateReport::new(&empty.into(), &trace);
+        assert!(report.valid);
+        assert_eq!(report.error, None);
+        assert!(report.to_text().ends_with("verdict: valid\n"));
+    }
+}
@@ -0,0 +1,133 @@
+// Statistics of a trace, from the commands alone. Time steps are
+// reconstructed by counting bots through Fission and FusionS, as in
+// `Trace::time_steps`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::bot::*;
+use super::prelude::*;
+use super::trace::*;
+
+#[derive(Debug, Default)]
+pub struct TraceStats {
+    pub cmds: usize,
+    pub bytes: usize,
+    pub time_steps: usize,
+    pub max_bots: usize,
+    /// The sum of the Manhattan lengths of SMove and LMove.
+    pub move_distance: u64,
+    pub fills: usize,
+    pub voids: usize,
+    /// Time steps which are in High harmonics.
+    pub high_time_steps: usize,
+    /// (count, bytes) by `Cmd::name`, of the commands which occur.
+    pub histogram: BTreeMap<&'static str, (usize, usize)>,
+}
+
+impl TraceStats {
+    pub fn new(trace: &Trace) -> TraceStats {
+        use self::Cmd::*;
+
+        let mut stats = TraceStats::default();
+        let mut high = false;
+        for step in trace.time_steps() {
+            stats.time_steps += 1;
+            stats.max_bots = stats.max_bots.max(step.len());
+            if high {
+                stats.high_time_steps += 1;
+            }
+            for cmd in step {
+                let bytes = Vec::<u8>::from(*cmd).len();
+                let entry = stats.histogram.entry(cmd.name()).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += bytes;
+                stats.cmds += 1;
+                stats.bytes += bytes;
+                match cmd {
+                    Flip => high = !high,
+                    SMove(lld) => stats.move_distance += lld.0.mlen(),
+                    LMove(sld1, sld2) => stats.move_distance += sld1.0.mlen() + sld2.0.mlen(),
+                    Fill(_) => stats.fills += 1,
+                    Void(_) => stats.voids += 1,
+                    _ => (),
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn average_bots(&self) -> f64 {
+        self.cmds as f64 / self.time_steps.max(1) as f64
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "commands: {}\nbytes: {}\ntime steps: {}\nbots: max {}, average {:.2}\n",
+            self.cmds,
+            self.bytes,
+            self.time_steps,
+            self.max_bots,
+            self.average_bots()
+        );
+        text += &format!(
+            "move distance: {}\nfills: {}\nvoids: {}\nHigh harmonics: {} time steps ({:.1}%)\n",
+            self.move_distance,
+            self.fills,
+            self.voids,
+            self.high_time_steps,
+            100.0 * self.high_time_steps as f64 / self.time_steps.max(1) as f64
+        );
+        text += &format!("{:<8} {:>8} {:>8}\n", "command", "count", "bytes");
+        for (name, (count, bytes)) in &self.histogram {
+            text += &format!("{:<8} {:>8} {:>8}\n", name, count, bytes);
+        }
+        text
+    }
+}
+
+pub fn print_stats(path: &Path) -> Result<()> {
+    print!("{}", TraceStats::new(&Trace::read(path)?).to_text());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trace_stats_test() {
+        use self::Cmd::*;
+
+        let trace = Trace {
+            cmds: vec![
+                Flip,
+                Fission(Near(CordDiff::new(1, 0, 0)), 0),
+                LMove(
+                    ShortLinear(CordDiff::new(0, 0, 2)),
+                    ShortLinear(CordDiff::new(0, 1, 0)),
+                ),
+                Fill(Near(CordDiff::new(0, 0, 1))),
+                FusionP(Near(CordDiff::new(1, 0, 0))),
+                FusionS(Near(CordDiff::new(-1, 0, 0))),
+                Flip,
+                Halt,
+            ],
+        };
+        let stats = TraceStats::new(&trace);
+        assert_eq!(stats.cmds, 8);
+        assert_eq!(stats.bytes, 10);
+        assert_eq!(stats.time_steps, 6);
+        assert_eq!(stats.max_bots, 2);
+        assert_eq!(stats.move_distance, 3);
+        assert_eq!((stats.fills, stats.voids), (1, 0));
+        assert_eq!(stats.high_time_steps, 4);
+        assert_eq!(stats.histogram["Flip"], (2, 2));
+        assert_eq!(stats.histogram["LMove"], (1, 2));
+        let text = stats.to_text();
+        assert!(text.contains("bots: max 2, average 1.33\n"));
+        assert!(text.contains("High harmonics: 4 time steps (66.7%)\n"));
+        assert!(text.contains("Fission         1        2\n"));
+        assert!(!text.contains("Wait"));
+    }
+}
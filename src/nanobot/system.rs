@@ -1,4 +1,5 @@
 use chrono::*;
+use std::cell::RefCell;
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::rc::Rc;
 
@@ -6,6 +7,8 @@ use super::bot::*;
 use super::matrix::*;
 use super::model::*;
 use super::prelude::*;
+use super::render::*;
+use super::rng::*;
 use super::target::*;
 use super::trace::*;
 
@@ -14,6 +17,8 @@ struct Volatile {
     r: usize,
     flip: bool,
     interfared_cords: Vec<bool>,
+    // Indices of `interfared_cords` which are set, to reset them cheaply.
+    touched: Vec<usize>,
     new_bots: Vec<Bot>,
     removed_bots: Vec<BotId>,
 }
@@ -24,13 +29,25 @@ impl Volatile {
             r,
             flip: Default::default(),
             interfared_cords: vec![false; r * r * r],
+            touched: vec![],
             new_bots: vec![],
             removed_bots: vec![],
         };
+        v.reset(bots);
+        v
+    }
+
+    // Same as `Volatile::new`, without allocating a matrix per time step.
+    fn reset(&mut self, bots: &[Bot]) {
+        for i in self.touched.drain(..) {
+            self.interfared_cords[i] = false;
+        }
+        self.flip = false;
+        self.new_bots.clear();
+        self.removed_bots.clear();
         for b in bots {
-            v.add_cord(b.pos);
+            self.add_cord(b.pos);
         }
-        v
     }
 
     fn is_interfared(&self, c: &Cord) -> bool {
@@ -48,16 +65,20 @@ impl Volatile {
     }
 
     fn add_cord(&mut self, cord: Cord) {
-        self.interfared_cords[cord.to_linear_index(self.r)] = true;
+        let i = cord.to_linear_index(self.r);
+        if !self.interfared_cords[i] {
+            self.interfared_cords[i] = true;
+            self.touched.push(i);
+        }
     }
 
     fn smove(&mut self, region: &Region) {
-        self.add_region(&region);
+        self.add_region(region);
     }
 
     fn lmove(&mut self, region1: &Region, region2: &Region) {
-        self.add_region(&region1);
-        self.add_region(&region2);
+        self.add_region(region1);
+        self.add_region(region2);
     }
 
     fn fussion(&mut self, new_bot: Bot) {
@@ -127,7 +148,7 @@ impl MoveCmds {
         }
         let mut res = vec![];
         let mut prev = cmds[0];
-        for cmd in cmds[1..].into_iter().cloned() {
+        for cmd in cmds[1..].iter().cloned() {
             match (prev, cmd) {
                 (SMove(lld1), SMove(lld2)) => {
                     let total = CordDiff::new(
@@ -159,7 +180,7 @@ impl MoveCmds {
         }
         let mut res = vec![];
         let mut prev = cmds[0];
-        for cmd in cmds[1..].into_iter().cloned() {
+        for cmd in cmds[1..].iter().cloned() {
             match (prev, cmd) {
                 (SMove(lld1), SMove(lld2)) => {
                     let total = CordDiff::new(
@@ -187,7 +208,6 @@ impl MoveCmds {
 
 pub struct MoveToNear {
     pub move_cmds: MoveCmds,
-    pub final_pos: Cord,
     pub target_nd: Near,
     pub target: Cord,
 }
@@ -200,14 +220,13 @@ impl From<Rc<MoveStep>> for MoveToNear {
         let move_cmds: MoveCmds = bot_final.clone().into();
         MoveToNear {
             move_cmds,
-            final_pos: bot_final.c,
             target_nd: Near(target - bot_final.c),
             target,
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Harmonics {
     Low,
     High,
@@ -225,6 +244,7 @@ pub struct System {
     reserved_fusion: HashMap<Cord, Cord>, // secondary -> primary
     pub records: Vec<Cmd>,
     start_datetime: DateTime<Local>,
+    rng: Option<RefCell<Rng>>,
 }
 
 impl System {
@@ -232,20 +252,35 @@ impl System {
         let bots = vec![Bot::new_at_origin()];
         let volatile = Volatile::new(model.r, &bots);
         System {
-            model_id: model.id,
+            model_id: model.id.clone(),
             energy: 0,
             harmonics: Harmonics::Low,
-            matrix: Matrix::new(&model),
-            priority_targets: PriorityTargets::new(&model),
+            matrix: Matrix::new(model),
+            priority_targets: PriorityTargets::new(model),
             bots,
             bot_index: 0,
             volatile,
             reserved_fusion: HashMap::new(),
             records: vec![],
             start_datetime: Local::now(),
+            rng: None,
         }
     }
 
+    /// Breaks ties between equally good moves randomly, instead of in the
+    /// fixed order of `CordDiff::gen_all_diff()`.
+    pub fn randomize(&mut self, seed: u64) {
+        self.rng = Some(RefCell::new(Rng::new(seed)));
+    }
+
+    fn ordered(&self, diffs: &[CordDiff]) -> Vec<CordDiff> {
+        let mut diffs = diffs.to_vec();
+        if let Some(rng) = self.rng.as_ref() {
+            rng.borrow_mut().shuffle(&mut diffs);
+        }
+        diffs
+    }
+
     pub fn current_bot(&self) -> &Bot {
         &self.bots[self.bot_index]
     }
@@ -260,6 +295,22 @@ impl System {
         PriorityTargets::top_priority_targets(&targets)
     }
 
+    /// The y slices which have bots, with volatile cells, for debugging.
+    pub fn render_bot_layers(&self) -> String {
+        let bots = self.bots.iter().map(|b| b.pos).collect::<Vec<_>>();
+        let mut ys = bots.iter().map(|b| b.y).collect::<Vec<_>>();
+        ys.sort();
+        ys.dedup();
+        ys.into_iter()
+            .map(|y| {
+                render_slice(&self.matrix, Axis::Y, y, &bots, |c| {
+                    self.volatile.is_interfared(&c)
+                })
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     pub fn is_interfared(&self, c: Cord) -> bool {
         self.matrix[c]
             // || self.bots.iter().any(|bot| bot.pos == c)
@@ -270,7 +321,7 @@ impl System {
         let pos = self.current_bot().pos;
         self.bots[self.bot_index + 1..]
             .iter()
-            .find(|b| self.reserved_fusion.get(&b.pos).is_none() && (b.pos - pos).is_near())
+            .find(|b| !self.reserved_fusion.contains_key(&b.pos) && (b.pos - pos).is_near())
     }
 
     pub fn is_current_bot_reserved_as_fusion_secondary(&self) -> Option<&Cord> {
@@ -282,8 +333,8 @@ impl System {
             None
         } else {
             let pos = self.current_bot().pos;
-            for diff in CordDiff::gen_all_diff() {
-                let c = pos + *diff;
+            for diff in self.ordered(CordDiff::gen_all_diff()) {
+                let c = pos + diff;
                 if !c.is_in_range(self.matrix.r) {
                     continue;
                 }
@@ -300,7 +351,7 @@ impl System {
 
     pub fn move_to_first_or_wait_cmd(&self, from: Cord, to: Cord) -> Cmd {
         let moves = self.move_to(from, to);
-        moves.cmds.get(0).cloned().unwrap_or(Cmd::Wait)
+        moves.cmds.first().cloned().unwrap_or(Cmd::Wait)
     }
 
     pub fn move_to_near(&self, from: Cord, targets: &HashSet<Cord>) -> Result<MoveToNear> {
@@ -315,8 +366,10 @@ impl System {
         let mut visited = HashSet::new();
         visited.insert(from);
 
+        let diffs = self.ordered(CordDiff::gen_all_diff());
+        let near_diffs = self.ordered(CordDiff::gen_all_near_diff());
         while let Some(current) = q.pop_front() {
-            for diff in CordDiff::gen_all_diff() {
+            for diff in &diffs {
                 let c = current.c + *diff;
                 if !c.is_in_range(r) {
                     continue;
@@ -331,7 +384,7 @@ impl System {
                     q.push_back(next);
                 }
             }
-            for diff in CordDiff::gen_all_near_diff() {
+            for diff in &near_diffs {
                 let c = current.c + *diff;
                 if !c.is_in_range(r) {
                     continue;
@@ -363,8 +416,9 @@ impl System {
         let mut visited = HashSet::new();
         visited.insert(from);
 
+        let diffs = self.ordered(CordDiff::gen_all_diff());
         while let Some(current) = q.pop_front() {
-            for diff in CordDiff::gen_all_diff() {
+            for diff in &diffs {
                 let c = current.c + *diff;
                 if !c.is_in_range(r) {
                     continue;
@@ -415,7 +469,7 @@ impl System {
         self.energy += (20 * self.bots.len()) as i64;
 
         self.apply_volatile();
-        self.volatile = Volatile::new(self.matrix.r, &self.bots);
+        self.volatile.reset(&self.bots);
     }
 
     fn apply_volatile(&mut self) {
@@ -428,9 +482,7 @@ impl System {
 
         let mut bots = self.bots.clone();
         bots.extend(self.volatile.new_bots.clone());
-        bots = bots.into_iter()
-            .filter(|b| !self.volatile.removed_bots.contains(&b.bid))
-            .collect();;
+        bots.retain(|b| !self.volatile.removed_bots.contains(&b.bid));
         bots.sort_by_key(|bot| bot.bid);
         self.bots = bots;
     }
@@ -488,7 +540,7 @@ impl System {
             self.current_bot().pos,
             cmd
         );
-        if (self.records.len() + 1) % 10_000 == 0 {
+        if (self.records.len() + 1).is_multiple_of(10_000) {
             info!(
                 "...solving model: {}, records.len: {}, start_datetime: {}, now: {}",
                 self.model_id.name(),
@@ -503,8 +555,8 @@ impl System {
         let mut halt = false;
         match cmd {
             Halt => {
-                assert!(self.bots[self.bot_index].pos.is_origin(), true);
-                assert_eq!(self.bots.len(), 1 as usize);
+                assert!(self.bots[self.bot_index].pos.is_origin());
+                assert_eq!(self.bots.len(), 1);
                 assert_eq!(self.harmonics, Harmonics::Low);
                 halt = true;
             }
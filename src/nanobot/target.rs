@@ -28,7 +28,7 @@ impl PriorityTargets {
         struct Entry {
             c: Cord,
             len: i64,
-        };
+        }
 
         for x in 0..r {
             for z in 0..r {
@@ -1,11 +1,35 @@
+use failure;
 use std;
 
 use super::bot::*;
+use super::contest;
 use super::prelude::*;
 
 use std::path::Path;
-use std::path::PathBuf;
 
+#[derive(Fail, Debug)]
+#[fail(display = "Parse error: line {}: {}", line, reason)]
+pub struct ParseError {
+    pub line: usize,
+    pub reason: String,
+}
+
+// The text format of a trace has one command per line, in the order of the
+// .nbt file, i.e. by time step and then by bot id:
+//
+//   # time step 0
+//   SMove x+12
+//   # time step 1
+//   Fission 0,1,0 5
+//   ...
+//   Halt
+//
+// Commands are `Halt`, `Wait`, `Flip`, `SMove <lld>`, `LMove <sld> <sld>`,
+// `Fission <nd> <m>`, `Fill <nd>`, `Void <nd>`, `FusionP <nd>` and
+// `FusionS <nd>`. A linear difference is an axis and a signed length, e.g.
+// `x+12` or `z-3`, and a near difference is `dx,dy,dz`, e.g. `0,-1,0`.
+// Everything after `#` is a comment, so the time step lines are only for
+// reading and need not be updated when editing.
 #[derive(Debug, Clone)]
 pub struct Trace {
     pub cmds: Vec<Cmd>,
@@ -13,37 +37,180 @@ pub struct Trace {
 
 impl std::fmt::Display for Trace {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        writeln!(f, "--start--").unwrap();
-        for cmd in &self.cmds {
-            writeln!(f, "{:?}", cmd).unwrap();
+        for (t, cmds) in self.time_steps().iter().enumerate() {
+            writeln!(f, "# time step {}", t)?;
+            for cmd in cmds.iter() {
+                writeln!(f, "{}", cmd)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Trace {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Trace> {
+        let mut cmds = vec![];
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let cmd = line.parse().map_err(|e: failure::Error| ParseError {
+                line: i + 1,
+                reason: e.to_string(),
+            })?;
+            cmds.push(cmd);
         }
-        writeln!(f, "--end--")
+        Ok(Trace { cmds })
     }
 }
 
 impl Trace {
+    pub fn read(path: impl AsRef<Path>) -> Result<Trace> {
+        Trace::decode(&std::fs::read(path)?)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Trace> {
+        let mut cmds = vec![];
+        let mut i = 0;
+        while i < bytes.len() {
+            let (cmd, len) = Cmd::decode(&bytes[i..])?;
+            cmds.push(cmd);
+            i += len;
+        }
+        Ok(Trace { cmds })
+    }
+
     pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
         std::fs::write(path, self.encode())?;
         Ok(())
     }
 
     pub fn write_to_trace_dir(&self, filename: &str) -> Result<()> {
-        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        path.push(&format!("contest/trace/{}", filename));
-        self.write_to(path)
+        let dir = contest::trace_dir();
+        std::fs::create_dir_all(&dir)?;
+        self.write_to(dir.join(filename))
     }
 
-    pub fn write_to_submit_dir(&self, name: &str) -> Result<()> {
-        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        path.push(&format!("contest/submit/{}.nbt", name));
-        info!("Writing best trace to: {}", path.display());
-        self.write_to(path)
+    /// Splits the commands by time step. Every bot has one command in a time
+    /// step, and Fission and FusionS change the number of bots. The last time
+    /// step is short if the trace ends in the middle of one.
+    pub fn time_steps(&self) -> Vec<&[Cmd]> {
+        let mut steps = vec![];
+        let mut bots = 1;
+        let mut i = 0;
+        while i < self.cmds.len() {
+            let step = &self.cmds[i..(i + bots).min(self.cmds.len())];
+            for cmd in step {
+                match cmd {
+                    Cmd::Fission(..) => bots += 1,
+                    Cmd::FusionS(_) => bots -= 1,
+                    _ => (),
+                }
+            }
+            // An invalid trace may fuse every bot.
+            bots = bots.max(1);
+            i += step.len();
+            steps.push(step);
+        }
+        steps
     }
 
-    fn encode(&self) -> Vec<u8> {
+    pub fn encode(&self) -> Vec<u8> {
         self.cmds.iter().fold(vec![], |mut acc, cmd| {
             acc.extend(Vec::<u8>::from(*cmd));
             acc
         })
     }
 }
+
+/// Writes the text format of the .nbt file `input` to `output`, or stdout.
+pub fn disasm(input: &Path, output: Option<&Path>) -> Result<()> {
+    let text = Trace::read(input)?.to_string();
+    match output {
+        Some(output) => std::fs::write(output, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Writes the .nbt file of the text format `input` to `output`.
+pub fn asm(input: &Path, output: &Path) -> Result<()> {
+    let trace: Trace = std::fs::read_to_string(input)?.parse()?;
+    info!("asm: {} commands", trace.cmds.len());
+    trace.write_to(output)
+}
+
+#[cfg(test)]
+mod test {
+    use self::Cmd::*;
+    use super::*;
+
+    #[test]
+    fn time_steps_test() {
+        let near = Near(CordDiff::new(0, 1, 0));
+        let trace = Trace {
+            cmds: vec![
+                Flip,
+                Fission(near, 0),
+                Wait,
+                Fill(Near(CordDiff::new(1, 0, 0))),
+                FusionP(near),
+                FusionS(Near(CordDiff::new(0, -1, 0))),
+                Flip,
+                Halt,
+            ],
+        };
+        let steps = trace.time_steps();
+        let lens = steps.iter().map(|s| s.len()).collect::<Vec<_>>();
+        assert_eq!(lens, vec![1, 1, 2, 2, 1, 1]);
+        assert_eq!(
+            steps[3],
+            &[FusionP(near), FusionS(Near(CordDiff::new(0, -1, 0)))]
+        );
+
+        // Ends in the middle of a time step.
+        let trace = Trace {
+            cmds: vec![Fission(near, 0), Wait],
+        };
+        let lens = trace
+            .time_steps()
+            .iter()
+            .map(|s| s.len())
+            .collect::<Vec<_>>();
+        assert_eq!(lens, vec![1, 1]);
+    }
+
+    #[test]
+    fn text_test() {
+        let trace = Trace {
+            cmds: vec![
+                Fission(Near(CordDiff::new(0, 1, 0)), 5),
+                SMove(LongLinear(CordDiff::new(0, 0, -4))),
+                LMove(
+                    ShortLinear(CordDiff::new(3, 0, 0)),
+                    ShortLinear(CordDiff::new(0, -5, 0)),
+                ),
+                Halt,
+            ],
+        };
+        let text = trace.to_string();
+        assert_eq!(
+            text,
+            "# time step 0\nFission 0,1,0 5\n# time step 1\nSMove z-4\nLMove x+3 y-5\n# time step 2\nHalt\n"
+        );
+        let parsed: Trace = text.parse().unwrap();
+        assert_eq!(parsed.encode(), trace.encode());
+
+        let parsed: Trace = "\n  Flip # comment\n# Wait\nHalt".parse().unwrap();
+        assert_eq!(parsed.cmds, vec![Flip, Halt]);
+
+        let e = "Flip\n\nFill 2,0,0\n".parse::<Trace>().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Parse error: line 3: Invalid command: near difference: 2,0,0"
+        );
+    }
+}
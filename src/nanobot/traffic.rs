@@ -0,0 +1,299 @@
+// Where bots go, and what they spend their time steps on, in a simulated
+// trace. A cell is visited once for each time step in which a bot stays on it
+// or moves through it.
+
+use std;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::bot::*;
+use super::image::*;
+use super::model::*;
+use super::prelude::*;
+use super::problem::*;
+use super::render::Axis;
+use super::simulator;
+use super::trace::*;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Utilization {
+    /// The time steps in which the bot is alive.
+    pub steps: usize,
+    pub wait: usize,
+    pub moving: usize,
+    pub filling: usize,
+    pub voiding: usize,
+    /// Flip, Fission, Fusion and Halt.
+    pub other: usize,
+}
+
+impl Utilization {
+    fn add(&mut self, cmd: Cmd) {
+        use self::Cmd::*;
+
+        self.steps += 1;
+        match cmd {
+            Wait => self.wait += 1,
+            SMove(_) | LMove(..) => self.moving += 1,
+            Fill(_) => self.filling += 1,
+            Void(_) => self.voiding += 1,
+            Halt | Flip | Fission(..) | FusionP(_) | FusionS(_) => self.other += 1,
+        }
+    }
+}
+
+pub struct Traffic {
+    pub r: usize,
+    /// By the linear index of cells.
+    pub visits: Vec<u32>,
+    pub bots: BTreeMap<BotId, Utilization>,
+}
+
+impl Traffic {
+    /// Simulates `trace`. Fails if it is invalid, or does not end with `tgt`.
+    pub fn new(src: Option<&Model>, tgt: Option<&Model>, trace: &Trace) -> Result<Traffic> {
+        use self::Cmd::*;
+
+        let mut sim = simulator::start(src, tgt)?;
+        let r = sim.r;
+        let mut visits = vec![0; r * r * r];
+        let mut bots = BTreeMap::new();
+        for cmd in &trace.cmds {
+            let bot = sim.bots.get(sim.bot_index).map(|b| (b.bid, b.pos));
+            sim.execute(*cmd)?;
+            // There is no bot only after Halt, where `execute` fails.
+            let (bid, pos) = bot.unwrap();
+            bots.entry(bid)
+                .or_insert_with(Utilization::default)
+                .add(*cmd);
+            visits[pos.to_linear_index(r)] += 1;
+            let diffs = match *cmd {
+                SMove(lld) => vec![lld.0],
+                LMove(sld1, sld2) => vec![sld1.0, sld2.0],
+                _ => vec![],
+            };
+            let mut c = pos;
+            for diff in diffs {
+                for _ in 0..diff.mlen() {
+                    c = c + diff.direc();
+                    visits[c.to_linear_index(r)] += 1;
+                }
+            }
+        }
+        simulator::check_end(&sim, tgt)?;
+        Ok(Traffic { r, visits, bots })
+    }
+
+    pub fn visits_at(&self, c: Cord) -> u32 {
+        self.visits[c.to_linear_index(self.r)]
+    }
+
+    /// The visits of the slice at `index` along `axis`, or the sum of every
+    /// slice, by (column, row) as in `render_slice`.
+    pub fn heat_slice(&self, axis: Axis, index: Option<i32>) -> Vec<Vec<u32>> {
+        let r = self.r as i32;
+        let indices = match index {
+            Some(index) => index..index + 1,
+            None => 0..r,
+        };
+        (0..r)
+            .map(|col| {
+                (0..r)
+                    .map(|row| {
+                        indices
+                            .clone()
+                            .map(|i| self.visits_at(axis.cord(i, col, row)))
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The `n` most visited cells, the most visited first.
+    pub fn hottest(&self, n: usize) -> Vec<(Cord, u32)> {
+        let r = self.r as i32;
+        let mut cells = (0..r)
+            .flat_map(|x| (0..r).flat_map(move |y| (0..r).map(move |z| Cord::new(x, y, z))))
+            .map(|c| (c, self.visits_at(c)))
+            .filter(|(_, v)| *v > 0)
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|(c, v)| (std::cmp::Reverse(*v), *c));
+        cells.truncate(n);
+        cells
+    }
+
+    pub fn to_text(&self) -> String {
+        let percent = |count: usize, steps: usize| 100.0 * count as f64 / steps.max(1) as f64;
+        let mut text = format!(
+            "{:>5} {:>6} {:>6} {:>6} {:>6} {:>6} {:>6}\n",
+            "bid", "steps", "wait%", "move%", "fill%", "void%", "other%"
+        );
+        let mut total = Utilization::default();
+        for (bid, u) in &self.bots {
+            text += &format!(
+                "{:>5} {:>6} {:>6.1} {:>6.1} {:>6.1} {:>6.1} {:>6.1}\n",
+                bid,
+                u.steps,
+                percent(u.wait, u.steps),
+                percent(u.moving, u.steps),
+                percent(u.filling, u.steps),
+                percent(u.voiding, u.steps),
+                percent(u.other, u.steps)
+            );
+            total.steps += u.steps;
+            total.wait += u.wait;
+            total.moving += u.moving;
+            total.filling += u.filling;
+            total.voiding += u.voiding;
+            total.other += u.other;
+        }
+        text += &format!(
+            "{:>5} {:>6} {:>6.1} {:>6.1} {:>6.1} {:>6.1} {:>6.1}\n",
+            "all",
+            total.steps,
+            percent(total.wait, total.steps),
+            percent(total.moving, total.steps),
+            percent(total.filling, total.steps),
+            percent(total.voiding, total.steps),
+            percent(total.other, total.steps)
+        );
+        text += &format!(
+            "visited cells: {}\nhottest cells:\n",
+            self.visits.iter().filter(|v| **v > 0).count()
+        );
+        for (c, v) in self.hottest(10) {
+            text += &format!("  ({}, {}, {}): {}\n", c.x, c.y, c.z, v);
+        }
+        text
+    }
+}
+
+// White for no visit, and from yellow to red for more visits.
+fn heat_color(visits: u32, max: u32) -> Rgb {
+    if visits == 0 {
+        return [255, 255, 255];
+    }
+    let t = visits as f64 / max.max(1) as f64;
+    [
+        (255.0 - 75.0 * t) as u8,
+        (230.0 * (1.0 - t)) as u8,
+        (80.0 * (1.0 - t)) as u8,
+    ]
+}
+
+/// An image of `heat_slice`, `t` pixels per cell with the origin at the
+/// bottom left.
+pub fn render_heatmap(heat: &[Vec<u32>], t: usize) -> Image {
+    let r = heat.len();
+    let max = heat
+        .iter()
+        .flat_map(|col| col.iter())
+        .cloned()
+        .max()
+        .unwrap_or(0);
+    let mut image = Image::new(r * t, r * t, [255, 255, 255]);
+    for (col, rows) in heat.iter().enumerate() {
+        for (row, visits) in rows.iter().enumerate() {
+            let color = heat_color(*visits, max);
+            for dy in 0..t {
+                for dx in 0..t {
+                    image.set(col * t + dx, (r - 1 - row) * t + dy, color);
+                }
+            }
+        }
+    }
+    image
+}
+
+/// Prints the utilization of bots and the hottest cells of the trace, and
+/// writes a heatmap image to `heatmap` if it is given.
+pub fn print_traffic(
+    src: Option<String>,
+    tgt: Option<String>,
+    trace: String,
+    axis: Axis,
+    index: Option<i32>,
+    heatmap: Option<&Path>,
+) -> Result<()> {
+    if src.is_none() && tgt.is_none() {
+        bail!("Either --src or --tgt is required");
+    }
+    let problem = ProblemFiles::new(src.map(PathBuf::from), tgt.map(PathBuf::from)).read()?;
+    let r = problem.r();
+    if let Some(index) = index {
+        if index < 0 || index as usize >= r {
+            bail!("Index out of range: {}, resolution: {}", index, r);
+        }
+    }
+    let traffic = Traffic::new(
+        problem.src.as_ref(),
+        problem.tgt.as_ref(),
+        &Trace::read(&trace)?,
+    )?;
+    print!("{}", traffic.to_text());
+    if let Some(heatmap) = heatmap {
+        if let Some(dir) = heatmap.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        render_heatmap(&traffic.heat_slice(axis, index), 4 * default_scale(r)).write_to(heatmap)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn traffic_test() {
+        use self::Cmd::*;
+
+        let trace = Trace {
+            cmds: vec![
+                Fission(Near(CordDiff::new(1, 0, 0)), 0),
+                LMove(
+                    ShortLinear(CordDiff::new(0, 0, 2)),
+                    ShortLinear(CordDiff::new(0, 1, 0)),
+                ),
+                Wait,
+                LMove(
+                    ShortLinear(CordDiff::new(0, -1, 0)),
+                    ShortLinear(CordDiff::new(0, 0, -2)),
+                ),
+                Wait,
+                FusionP(Near(CordDiff::new(1, 0, 0))),
+                FusionS(Near(CordDiff::new(-1, 0, 0))),
+                Halt,
+            ],
+        };
+        let tgt = Model::from_cords(ModelId::Assemble(None), 3, Default::default());
+        let traffic = Traffic::new(None, Some(&tgt), &trace).unwrap();
+        let unfinished = Trace {
+            cmds: trace.cmds[..trace.cmds.len() - 1].to_vec(),
+        };
+        assert!(Traffic::new(None, Some(&tgt), &unfinished).is_err());
+        let bot1 = traffic.bots[&1];
+        assert_eq!(bot1.steps, 5);
+        assert_eq!((bot1.wait, bot1.moving, bot1.other), (0, 2, 3));
+        assert_eq!((traffic.bots[&2].steps, traffic.bots[&2].wait), (3, 2));
+
+        // The origin: 4 time steps starting there, and 1 moving into it.
+        assert_eq!(traffic.visits_at(Cord::new(0, 0, 0)), 5);
+        assert_eq!(traffic.visits_at(Cord::new(0, 0, 1)), 2);
+        assert_eq!(traffic.visits_at(Cord::new(0, 1, 2)), 2);
+        assert_eq!(traffic.visits_at(Cord::new(1, 0, 0)), 3);
+        assert_eq!(traffic.hottest(1), vec![(Cord::new(0, 0, 0), 5)]);
+        assert!(traffic.to_text().contains("  all      8"));
+
+        let heat = traffic.heat_slice(Axis::Y, None);
+        assert_eq!(heat[0][0], 5);
+        assert_eq!(heat[0][2], 2 + 2);
+        assert_eq!(heat[1][0], 3);
+        assert_eq!(traffic.heat_slice(Axis::Y, Some(1))[0][2], 2);
+        let image = render_heatmap(&heat, 2);
+        assert_eq!(image.width, 2 * traffic.r);
+        assert_eq!(image.get(0, image.height - 1), heat_color(5, 5));
+        assert_eq!(image.get(image.width - 1, 0), [255, 255, 255]);
+    }
+}